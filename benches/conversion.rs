@@ -0,0 +1,221 @@
+//! Benchmarks for converting large, generated translation files.
+//!
+//! Run with `cargo bench`. Each input file is generated once into the system temp directory,
+//! varying the number of rows and locales, the size of each cell, how many cells are quoted
+//! multi-line text, and how many records repeat an earlier translation key.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use translocate::{generate_json, generate_json_fast, get_file_reader, Config, Diagnostic};
+
+/// Locales supported by the strict `generate_json` strategy.
+const STRICT_LOCALES: [&str; 10] = [
+    "da_DK", "de_DE", "en_US", "es_ES", "fr_FR", "it_IT", "nl_NL", "pt_BR", "pt_PT", "sv_SE",
+];
+
+/// Shape of a generated translation file.
+#[derive(Clone, Copy)]
+struct Shape {
+    rows: usize,
+    locales: usize,
+    cell_len: usize,
+    /// One in every `multiline_every` cells is quoted text spanning several lines. 0 disables.
+    multiline_every: usize,
+    /// One in every `duplicate_every` records repeats an earlier key. 0 disables.
+    duplicate_every: usize,
+}
+
+impl Shape {
+    const fn new(rows: usize, locales: usize) -> Shape {
+        Shape {
+            rows,
+            locales,
+            cell_len: 24,
+            multiline_every: 0,
+            duplicate_every: 0,
+        }
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "{}rows_{}locales_{}chars_ml{}_dup{}",
+            self.rows, self.locales, self.cell_len, self.multiline_every, self.duplicate_every
+        )
+    }
+
+    fn locale_names(&self) -> Vec<String> {
+        if self.locales == STRICT_LOCALES.len() {
+            STRICT_LOCALES
+                .iter()
+                .map(|locale| locale.to_string())
+                .collect()
+        } else {
+            (0..self.locales)
+                .map(|idx| format!("l{idx:02}_XX"))
+                .collect()
+        }
+    }
+
+    /// Writes the generated CSV file, returning its path and size in bytes.
+    fn generate(&self) -> (PathBuf, u64) {
+        let dir = bench_dir();
+        let path = dir.join(format!("{}.csv", self.name()));
+        let mut csv = String::with_capacity(self.rows * self.locales * (self.cell_len + 4));
+        let text = "lorem ipsum dolor sit amet ".repeat(self.cell_len / 27 + 1);
+        let text = &text[..self.cell_len];
+
+        csv.push_str("id");
+        for locale in self.locale_names() {
+            write!(csv, ",{locale}").unwrap();
+        }
+        csv.push('\n');
+
+        for row in 0..self.rows {
+            let key = if self.duplicate_every > 0 && row % self.duplicate_every == 0 && row > 0 {
+                row / 2
+            } else {
+                row
+            };
+            write!(csv, "section.{}.key_{key}", key % 97).unwrap();
+
+            for locale in 0..self.locales {
+                let cell = row * self.locales + locale;
+                if self.multiline_every > 0 && cell % self.multiline_every == 0 {
+                    write!(csv, ",\"{text}\n\"\"{locale}\"\",\n{text}\"").unwrap();
+                } else {
+                    write!(csv, ",{text} {locale}").unwrap();
+                }
+            }
+            csv.push('\n');
+        }
+
+        fs::write(&path, &csv).unwrap();
+        (path, csv.len() as u64)
+    }
+}
+
+fn bench_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join("translocate_benches");
+    fs::create_dir_all(dir.join("out")).unwrap();
+    dir
+}
+
+fn config(output_dir: &str) -> Config<'_> {
+    Config {
+        output_dir,
+        ..Config::default()
+    }
+}
+
+fn bench_generate_json_fast(c: &mut Criterion) {
+    let shapes = [
+        Shape::new(1_000, 10),
+        Shape::new(10_000, 10),
+        Shape::new(100_000, 10),
+        Shape::new(10_000, 2),
+        Shape::new(10_000, 40),
+        Shape {
+            cell_len: 256,
+            ..Shape::new(10_000, 10)
+        },
+        Shape {
+            multiline_every: 4,
+            ..Shape::new(10_000, 10)
+        },
+        Shape {
+            duplicate_every: 10,
+            ..Shape::new(10_000, 10)
+        },
+    ];
+    let out = bench_dir().join("out");
+    let config = config(out.to_str().unwrap());
+    let mut group = c.benchmark_group("generate_json_fast");
+    group.sample_size(10);
+
+    for shape in shapes {
+        let (path, bytes) = shape.generate();
+        let path = path.to_str().unwrap();
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_function(BenchmarkId::from_parameter(shape.name()), |b| {
+            b.iter(|| {
+                let mut reader = get_file_reader(path, &config).unwrap();
+                let headings = reader.headers().unwrap().clone();
+                generate_json_fast(&mut reader, &headings, &config, &mut |_: Diagnostic| {})
+                    .unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_generate_json(c: &mut Criterion) {
+    let shapes = [
+        Shape::new(1_000, STRICT_LOCALES.len()),
+        Shape::new(10_000, STRICT_LOCALES.len()),
+        Shape {
+            multiline_every: 4,
+            duplicate_every: 10,
+            ..Shape::new(10_000, STRICT_LOCALES.len())
+        },
+    ];
+    let out = bench_dir().join("out");
+    let config = config(out.to_str().unwrap());
+    let mut group = c.benchmark_group("generate_json");
+    group.sample_size(10);
+
+    for shape in shapes {
+        let (path, bytes) = shape.generate();
+        let path = path.to_str().unwrap();
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_function(BenchmarkId::from_parameter(shape.name()), |b| {
+            b.iter(|| {
+                let mut reader = get_file_reader(path, &config).unwrap();
+                let headings = reader.headers().unwrap().clone();
+                generate_json(&mut reader, &headings, &config, &mut |_: Diagnostic| {}).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_binary(c: &mut Criterion) {
+    let shapes = [
+        Shape::new(10_000, 10),
+        Shape {
+            multiline_every: 4,
+            duplicate_every: 10,
+            ..Shape::new(100_000, 10)
+        },
+    ];
+    let out = bench_dir().join("out");
+    let mut group = c.benchmark_group("binary");
+    group.sample_size(10);
+
+    for shape in shapes {
+        let (path, bytes) = shape.generate();
+        group.throughput(Throughput::Bytes(bytes));
+        group.bench_function(BenchmarkId::from_parameter(shape.name()), |b| {
+            b.iter(|| {
+                let status = Command::new(env!("CARGO_BIN_EXE_translocate"))
+                    .arg("-o")
+                    .arg(&out)
+                    .arg(&path)
+                    .output()
+                    .unwrap()
+                    .status;
+                assert!(status.success());
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_generate_json_fast,
+    bench_generate_json,
+    bench_binary
+);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+use crate::dialect::Dialect;
+use crate::translations::{DuplicateKey, Location, OverwrittenKey};
+use crate::writers::WrittenFile;
+
+/// Events reported while converting an input file and writing the output files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The delimiter, quote character or line ending of the input file was detected, because
+    /// it wasn't configured.
+    DialectDetected(Dialect),
+    /// A path given on the command line contained a `~` or `$` character, which shells expand
+    /// unless the path is quoted. The path is used as is, as a literal file name.
+    UnexpandedPath {
+        /// The path containing the character.
+        path: String,
+        /// The `~` or `$` character found.
+        character: char,
+    },
+    /// A translation key was given a new value by a later record.
+    DuplicateKey(OverwrittenKey),
+    /// Translation keys which appear in more than one record, with every line each appears on.
+    /// Reported once every record is read, whether or not a later record replaced any values.
+    DuplicateKeys(Vec<DuplicateKey>),
+    /// A translation key had no value for a locale.
+    EmptyValue {
+        /// Locale (column heading) the value was missing from.
+        locale: String,
+        /// The translation key with the empty value.
+        key: String,
+        /// Location of the record with the empty value.
+        location: Location,
+    },
+    /// Every record in the input file was converted.
+    Converted {
+        /// Number of records read, excluding the heading row.
+        records: usize,
+        /// Number of records which overwrote an existing translation key.
+        overwritten: usize,
+    },
+    /// An output file was written.
+    FileWritten(WrittenFile),
+}
+
+/// Receives [`Diagnostic`] events as they occur, so callers decide how (or if) to present them.
+///
+/// Implemented for `Vec<Diagnostic>` to collect every event, and for closures taking a `Diagnostic`.
+pub trait DiagnosticSink {
+    /// Handles a single diagnostic event.
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic)
+    }
+}
+
+impl<F: FnMut(Diagnostic)> DiagnosticSink for F {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
@@ -0,0 +1,226 @@
+//! Detects the delimiter, quote character and line ending of CSV input which doesn't use the
+//! defaults, such as the `;` delimited files exported by Excel in many European locales.
+
+use crate::Error;
+use serde::Serialize;
+use std::io::{self, Read, Seek};
+
+/// Number of bytes at the start of the input inspected when detecting its dialect.
+const SNIFF_LEN: u64 = 8 * 1024;
+
+/// Delimiters which can be detected, in order of preference when equally likely.
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// Line ending used between records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum LineEnding {
+    /// `\n`
+    #[serde(rename = "LF")]
+    Lf,
+    /// `\r\n`
+    #[serde(rename = "CRLF")]
+    CrLf,
+    /// `\r`
+    #[serde(rename = "CR")]
+    Cr,
+}
+
+/// The format of a CSV file, as detected by [`Dialect::sniff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dialect {
+    /// Column delimiter.
+    pub delimiter: u8,
+    /// Character used to quote cells containing delimiters or line endings.
+    pub quote: u8,
+    /// Line ending used between records.
+    pub line_ending: LineEnding,
+}
+
+impl Dialect {
+    /// Detects the dialect from the first few kilobytes of `input`, then moves `input` back to
+    /// where it started. Returns `None` if no delimiter is used consistently, such as when the
+    /// input only has one column.
+    ///
+    /// * `input` - the input file, positioned at its start
+    pub fn sniff<R: Read + Seek>(input: &mut R) -> Result<Option<Dialect>, Error> {
+        let start = input.stream_position()?;
+        let mut sample = Vec::new();
+        input.by_ref().take(SNIFF_LEN).read_to_end(&mut sample)?;
+        input.seek(io::SeekFrom::Start(start))?;
+
+        Ok(Dialect::from_sample(
+            &sample,
+            sample.len() as u64 == SNIFF_LEN,
+        ))
+    }
+
+    /// Detects the dialect of a sample of CSV text.
+    ///
+    /// * `sample` - the start of the input
+    /// * `truncated` - whether the sample ends part way through the input
+    fn from_sample(sample: &[u8], truncated: bool) -> Option<Dialect> {
+        let mut lines = split_lines(sample, b'"');
+        if truncated && lines.len() > 1 {
+            lines.pop();
+        }
+
+        let (delimiter, ..) = DELIMITERS
+            .iter()
+            // Reversed so ties keep the earliest delimiter, as `max_by_key` returns the last maximum.
+            .rev()
+            .filter_map(|delimiter| {
+                let counts = lines
+                    .iter()
+                    .map(|line| count_unquoted(line, *delimiter, b'"'))
+                    .collect::<Vec<usize>>();
+                let heading_count = *counts.first()?;
+                let consistent = counts
+                    .iter()
+                    .filter(|count| **count == heading_count)
+                    .count();
+                (heading_count > 0).then_some((*delimiter, consistent, heading_count))
+            })
+            // Prefer the delimiter found the same number of times on the most lines, then the one
+            // splitting the heading into the most columns.
+            .max_by_key(|(_, consistent, columns)| (*consistent, *columns))?;
+
+        Some(Dialect {
+            delimiter,
+            quote: detect_quote(&lines, delimiter),
+            line_ending: detect_line_ending(sample),
+        })
+    }
+}
+
+/// Splits text into lines, ignoring line endings inside quoted cells.
+fn split_lines(sample: &[u8], quote: u8) -> Vec<&[u8]> {
+    let mut lines = Vec::new();
+    let mut quoted = false;
+    let mut line_start = 0;
+
+    for (idx, byte) in sample.iter().enumerate() {
+        if *byte == quote {
+            quoted = !quoted;
+        } else if !quoted && (*byte == b'\n' || *byte == b'\r') {
+            if idx > line_start {
+                lines.push(&sample[line_start..idx]);
+            }
+            line_start = idx + 1;
+        }
+    }
+    if line_start < sample.len() {
+        lines.push(&sample[line_start..]);
+    }
+
+    lines
+}
+
+/// Counts the times `delimiter` appears in a line outside of quoted cells.
+fn count_unquoted(line: &[u8], delimiter: u8, quote: u8) -> usize {
+    let mut quoted = false;
+
+    line.iter()
+        .filter(|byte| {
+            if **byte == quote {
+                quoted = !quoted;
+            }
+            !quoted && **byte == delimiter
+        })
+        .count()
+}
+
+/// Picks `'` as the quote character if it wraps more cells than `"` does.
+fn detect_quote(lines: &[&[u8]], delimiter: u8) -> u8 {
+    let wrapped_cells = |quote: u8| {
+        lines
+            .iter()
+            .flat_map(|line| line.split(|byte| *byte == delimiter))
+            .filter(|cell| cell.len() > 1 && cell[0] == quote && cell[cell.len() - 1] == quote)
+            .count()
+    };
+
+    if wrapped_cells(b'\'') > wrapped_cells(b'"') {
+        b'\''
+    } else {
+        b'"'
+    }
+}
+
+/// Finds the line ending of the first line.
+fn detect_line_ending(sample: &[u8]) -> LineEnding {
+    match sample
+        .iter()
+        .position(|byte| *byte == b'\n' || *byte == b'\r')
+    {
+        Some(idx) if sample[idx] == b'\n' => LineEnding::Lf,
+        Some(idx) if sample.get(idx + 1) == Some(&b'\n') => LineEnding::CrLf,
+        Some(_) => LineEnding::Cr,
+        None => LineEnding::Lf,
+    }
+}
+
+#[cfg(test)]
+mod dialect_tests {
+    use super::{Dialect, LineEnding};
+    use pretty_assertions::assert_eq;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn it_detects_the_delimiter_used_on_every_line() {
+        let sample = b"id;en_US;fr_FR\r\napp.title;\"Hello, world\";\"Bonjour, le monde\"\r\n";
+
+        assert_eq!(
+            Dialect::from_sample(sample, false),
+            Some(Dialect {
+                delimiter: b';',
+                quote: b'"',
+                line_ending: LineEnding::CrLf
+            })
+        );
+    }
+
+    #[test]
+    fn it_detects_tabs_pipes_and_single_quotes() {
+        let tabs = b"id\ten_US\nlist\t'one, two'\n";
+        let pipes = b"id|en_US|fr_FR\rapp|Hi|Salut\r";
+
+        assert_eq!(
+            Dialect::from_sample(tabs, false),
+            Some(Dialect {
+                delimiter: b'\t',
+                quote: b'\'',
+                line_ending: LineEnding::Lf
+            })
+        );
+        assert_eq!(
+            Dialect::from_sample(pipes, false).map(|dialect| dialect.delimiter),
+            Some(b'|')
+        );
+    }
+
+    #[test]
+    fn it_ignores_delimiters_inside_quoted_multi_line_cells() {
+        let sample = b"id,en_US\nintro,\"Line one;\nline two; three\"\nouttro,Bye\n";
+
+        assert_eq!(
+            Dialect::from_sample(sample, false).map(|dialect| dialect.delimiter),
+            Some(b',')
+        );
+    }
+
+    #[test]
+    fn it_detects_nothing_for_a_single_column() {
+        assert_eq!(Dialect::from_sample(b"id\napp.title\n", false), None);
+    }
+
+    #[test]
+    fn it_rewinds_the_input_after_sniffing() {
+        let mut input = Cursor::new(b"id;en_US\napp;Hello\n".to_vec());
+        let dialect = Dialect::sniff(&mut input).unwrap();
+        let mut text = String::new();
+        input.read_to_string(&mut text).unwrap();
+
+        assert_eq!(dialect.map(|dialect| dialect.delimiter), Some(b';'));
+        assert_eq!(text, "id;en_US\napp;Hello\n");
+    }
+}
@@ -0,0 +1,180 @@
+//! Detects the character encoding of input files, and transcodes anything other than UTF-8
+//! before it is parsed.
+
+use crate::Error;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Number of bytes at the start of a file inspected when detecting its encoding.
+const SNIFF_LEN: usize = 8 * 1024;
+
+/// An opened input file. UTF-8 files are read directly, while files in any other encoding
+/// are transcoded to UTF-8 in memory.
+#[derive(Debug)]
+pub enum InputFile {
+    /// A UTF-8 file, read as is.
+    Utf8(File),
+    /// The contents of a file transcoded to UTF-8.
+    Transcoded(Cursor<Vec<u8>>),
+}
+
+impl InputFile {
+    /// Opens the file at `path`, transcoding it to UTF-8 if needed.
+    ///
+    /// * `path` - location of the input file
+    /// * `encoding` - encoding of the file, or `None` to detect it from the start of the file
+    pub fn open(path: &Path, encoding: Option<&'static Encoding>) -> Result<InputFile, Error> {
+        let mut file = File::open(path).map_err(Error::file(path))?;
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None => {
+                let mut start = Vec::with_capacity(SNIFF_LEN);
+                file.by_ref()
+                    .take(SNIFF_LEN as u64)
+                    .read_to_end(&mut start)?;
+                file.rewind()?;
+                detect_encoding(&start)
+            }
+        };
+
+        if encoding == UTF_8 {
+            // The CSV parser already skips a UTF-8 byte order mark.
+            return Ok(InputFile::Utf8(file));
+        }
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let (text, _) = encoding.decode_with_bom_removal(&bytes);
+
+        Ok(InputFile::Transcoded(Cursor::new(
+            text.into_owned().into_bytes(),
+        )))
+    }
+}
+
+impl Read for InputFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            InputFile::Utf8(file) => file.read(buf),
+            InputFile::Transcoded(text) => text.read(buf),
+        }
+    }
+}
+
+impl Seek for InputFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            InputFile::Utf8(file) => file.seek(pos),
+            InputFile::Transcoded(text) => text.seek(pos),
+        }
+    }
+}
+
+/// Parses an encoding name such as `utf-8`, `utf-16le`, `windows-1252` or `latin1`.
+pub(crate) fn parse_encoding(label: &str) -> Result<&'static Encoding, Error> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+        Error::InvalidConfig(format!(
+            "unknown encoding \"{label}\". Try `utf-8`, `utf-16le` or `windows-1252`."
+        ))
+    })
+}
+
+/// Guesses the encoding of a file from its first bytes. A byte order mark is trusted first.
+/// Without one, text with a zero byte in most odd or even positions is taken to be UTF-16,
+/// valid UTF-8 is taken as UTF-8, and anything else is assumed to be Windows-1252, which is
+/// what most spreadsheet programs on Windows export.
+///
+/// * `start` - the first bytes of the file
+fn detect_encoding(start: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(start) {
+        return encoding;
+    }
+
+    let pairs = start.len() / 2;
+    if pairs > 0 {
+        let zeros_at = |offset: usize| {
+            start
+                .iter()
+                .skip(offset)
+                .step_by(2)
+                .filter(|byte| **byte == 0)
+                .count()
+        };
+        if zeros_at(1) * 2 > pairs {
+            return UTF_16LE;
+        }
+        if zeros_at(0) * 2 > pairs {
+            return UTF_16BE;
+        }
+    }
+
+    match std::str::from_utf8(start) {
+        Ok(_) => UTF_8,
+        // The sniffed bytes may end part way through a character.
+        Err(error) if error.error_len().is_none() => UTF_8,
+        Err(_) => WINDOWS_1252,
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::{detect_encoding, parse_encoding, InputFile};
+    use crate::Error;
+    use encoding_rs::{UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+    use pretty_assertions::assert_eq;
+    use std::{fs, io::Read};
+
+    #[test]
+    fn it_detects_the_encoding_of_the_start_of_a_file() {
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFid,en_US\n"), UTF_8);
+        assert_eq!(detect_encoding(b"\xFF\xFEi\0d\0"), UTF_16LE);
+        assert_eq!(detect_encoding(b"i\0d\0,\0e\0n\0"), UTF_16LE);
+        assert_eq!(detect_encoding(b"\0i\0d\0,\0e\0n"), UTF_16BE);
+        assert_eq!(detect_encoding("id,fr_FR\nfaq,Où\n".as_bytes()), UTF_8);
+        // "é" cut off after its first byte
+        assert_eq!(detect_encoding(b"id,fr_FR\nfaq,\xC3"), UTF_8);
+        assert_eq!(detect_encoding(b"id,fr_FR\nfaq,O\xF9\n"), WINDOWS_1252);
+        assert_eq!(detect_encoding(b""), UTF_8);
+    }
+
+    #[test]
+    fn it_transcodes_utf_16_and_windows_1252_files() {
+        let dir = std::env::temp_dir().join("translocate_encoding_test");
+        fs::create_dir_all(&dir).unwrap();
+        let utf16 = dir.join("utf16.csv");
+        let mut data = vec![0xFF, 0xFE];
+        "id,fr_FR\nfaq,Où\n"
+            .encode_utf16()
+            .for_each(|unit| data.extend_from_slice(&unit.to_le_bytes()));
+        fs::write(&utf16, data).unwrap();
+        let cp1252 = dir.join("cp1252.csv");
+        fs::write(&cp1252, b"id,fr_FR\nfaq,O\xF9\n").unwrap();
+
+        let mut text = String::new();
+        InputFile::open(&utf16, None)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        let mut text_1252 = String::new();
+        InputFile::open(&cp1252, None)
+            .unwrap()
+            .read_to_string(&mut text_1252)
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(text, "id,fr_FR\nfaq,Où\n");
+        assert_eq!(text_1252, "id,fr_FR\nfaq,Où\n");
+    }
+
+    #[test]
+    fn it_parses_encoding_names() {
+        assert_eq!(parse_encoding("UTF-16LE").unwrap(), UTF_16LE);
+        assert_eq!(parse_encoding("latin1").unwrap(), WINDOWS_1252);
+        assert!(matches!(
+            parse_encoding("klingon"),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::translations::DuplicateKey;
+use crate::values::ValueType;
+use std::path::{Path, PathBuf};
+use std::{fmt, io};
+
+/// Errors which can occur while reading, converting or writing translations.
+#[derive(Debug)]
+pub enum Error {
+    /// The input file could not be parsed. Contains the position of the failing record when known.
+    Csv(csv::Error),
+    /// A configuration option had a value which can't be used.
+    InvalidConfig(String),
+    /// A column heading is not one of the locales supported by strict conversion.
+    UnknownLocale(String),
+    /// Translation keys which were defined more than once when duplicates are not allowed.
+    DuplicateKeys(Vec<DuplicateKey>),
+    /// A value didn't match the type declared for its translation key.
+    InvalidValue {
+        /// The translation key with the invalid value.
+        key: String,
+        /// Locale (column heading) of the invalid value.
+        locale: String,
+        /// Line of the record with the invalid value.
+        line: u64,
+        /// Type declared for the translation key.
+        value_type: ValueType,
+    },
+    /// A translation key can't be written in the output format, such as a key which is written
+    /// with the same identifier as another key.
+    InvalidKey {
+        /// The translation key which can't be written.
+        key: String,
+        /// Why the key can't be written.
+        reason: String,
+    },
+    /// Opening, creating or writing a file or directory failed.
+    File {
+        /// Location of the file or directory.
+        path: PathBuf,
+        /// Why it couldn't be used.
+        error: io::Error,
+    },
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// Translations or a report could not be serialized as JSON.
+    Json(serde_json::Error),
+    /// A spreadsheet input file could not be opened or read.
+    Spreadsheet(String),
+}
+
+impl Error {
+    /// Returns a function which converts an I/O error into an [`Error::File`] for `path`, for
+    /// use with `map_err`.
+    pub(crate) fn file(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |error| Error::File {
+            path: path.to_owned(),
+            error,
+        }
+    }
+
+    /// Returns the position in the input file where parsing failed, if known.
+    pub fn position(&self) -> Option<&csv::Position> {
+        match self {
+            Error::Csv(err) => err.position(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Csv(err) => write!(f, "{err}"),
+            Error::InvalidConfig(msg) => write!(f, "invalid configuration: {msg}"),
+            Error::UnknownLocale(locale) => write!(f, "unsupported locale \"{locale}\""),
+            Error::DuplicateKeys(keys) => {
+                write!(f, "{} duplicate translation keys found:", keys.len())?;
+                for dupe in keys {
+                    let lines = dupe
+                        .lines
+                        .iter()
+                        .map(|line| line.to_string())
+                        .collect::<Vec<String>>();
+                    write!(f, "\n  \"{}\" (lines {})", dupe.key, lines.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::InvalidValue {
+                key,
+                locale,
+                line,
+                value_type,
+            } => write!(
+                f,
+                "value of \"{key}\" for {locale} on line {line} is not a valid {value_type}"
+            ),
+            Error::InvalidKey { key, reason } => {
+                write!(f, "translation key \"{key}\" can't be written: {reason}")
+            }
+            Error::File { path, error } => write!(f, "{}: {error}", path.display()),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Json(err) => write!(f, "JSON could not be written: {err}"),
+            Error::Spreadsheet(msg) => write!(f, "spreadsheet could not be read: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Csv(err) => Some(err),
+            Error::File { error, .. } => Some(error),
+            Error::Io(err) => Some(err),
+            Error::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        if err.is_io_error() {
+            match err.into_kind() {
+                csv::ErrorKind::Io(io_err) => Error::Io(io_err),
+                _ => unreachable!("csv::Error::is_io_error was true"),
+            }
+        } else {
+            Error::Csv(err)
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
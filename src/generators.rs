@@ -1,256 +1,450 @@
-use crate::translations::{FormatTranslation, LangData, Translations};
-use crate::{get_file_location, Config};
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::translations::{
+    DuplicateKey, FormatTranslation, Location, OverwrittenKey, TranslationRecord, Translations,
+};
+use crate::values::ValueType;
+use crate::{Config, DuplicatePolicy, Error};
 use csv::{Reader, StringRecord};
-use serde_json::{to_string_pretty, Map, Value};
-use std::collections::HashMap;
-use std::fs::{create_dir_all, File};
-use std::io::Write;
-use yansi::Paint;
+use std::{borrow::Cow, collections::BTreeMap, io};
 
-const DUPE_KEY_NOTICE: &str = "translation keys overwritten during conversion.\n";
+/// A source of input records, such as a CSV reader or the rows of a spreadsheet, which
+/// can be converted into translations. The heading row is not included.
+pub trait RecordSource {
+    /// Reads the next record into `record`, returning `false` once there are no records left.
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error>;
+}
 
-/// Generate JSON files from CSV using structured deserialization
+impl<R: io::Read> RecordSource for Reader<R> {
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error> {
+        Ok(Reader::read_record(self, record)?)
+    }
+}
+
+/// Tracks every line each translation key appears on, so every duplicated key can be listed
+/// whichever [`DuplicatePolicy`] is used.
+struct KeyLines {
+    policy: DuplicatePolicy,
+    /// First line each translation key appears on, by key index.
+    first: Vec<u64>,
+    /// Later lines of the translation keys which appear more than once, by key index.
+    repeated: BTreeMap<usize, Vec<u64>>,
+}
+
+impl KeyLines {
+    fn new(policy: DuplicatePolicy) -> KeyLines {
+        KeyLines {
+            policy,
+            first: Vec::new(),
+            repeated: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a line the key at `key_idx` appears on.
+    fn add(&mut self, key_idx: usize, line: u64) {
+        if key_idx == self.first.len() {
+            self.first.push(line);
+        } else {
+            self.repeated.entry(key_idx).or_default().push(line);
+        }
+    }
+
+    /// Reports every key which appeared on more than one line, or returns an error listing
+    /// them when duplicates are not allowed.
+    fn check(
+        self,
+        translations: &Translations,
+        diagnostics: &mut dyn DiagnosticSink,
+    ) -> Result<(), Error> {
+        if self.repeated.is_empty() {
+            return Ok(());
+        }
+
+        let mut duplicates = translations
+            .keys()
+            .zip(translations.key_indices())
+            .filter_map(|(key, key_idx)| {
+                let repeated = self.repeated.get(&key_idx)?;
+                Some(DuplicateKey {
+                    key: key.to_owned(),
+                    lines: std::iter::once(self.first[key_idx])
+                        .chain(repeated.iter().copied())
+                        .collect(),
+                })
+            })
+            .collect::<Vec<DuplicateKey>>();
+        duplicates.sort_by_key(|dupe| dupe.lines[0]);
+
+        match self.policy {
+            DuplicatePolicy::Error => Err(Error::DuplicateKeys(duplicates)),
+            _ => {
+                diagnostics.report(Diagnostic::DuplicateKeys(duplicates));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Finds the columns which describe each record's key rather than holding a locale: the type,
+/// description and context columns.
+struct KeyColumns {
+    type_column: Option<usize>,
+    description_column: Option<usize>,
+    context_column: Option<usize>,
+}
+
+impl KeyColumns {
+    fn new(config: &Config, headings: &StringRecord) -> KeyColumns {
+        let position = |name: &str| headings.iter().position(|heading| heading.trim() == name);
+
+        KeyColumns {
+            type_column: config.type_column.and_then(position),
+            description_column: config.description_column.and_then(position),
+            context_column: config.context_column.and_then(position),
+        }
+    }
+
+    /// Returns `true` if the column at `column_idx` describes keys, rather than holding a locale.
+    fn is_key_column(&self, column_idx: usize) -> bool {
+        [
+            self.type_column,
+            self.description_column,
+            self.context_column,
+        ]
+        .contains(&Some(column_idx))
+    }
+
+    /// Returns the description of the record's key, if the description column has one.
+    fn description<'r>(&self, record: &'r StringRecord) -> Option<&'r str> {
+        self.description_column
+            .and_then(|idx| record.get(idx))
+            .filter(|description| !description.is_empty())
+    }
+
+    /// Returns the context of the record's key, if the context column has one.
+    fn context<'r>(&self, record: &'r StringRecord) -> Option<&'r str> {
+        self.context_column
+            .and_then(|idx| record.get(idx))
+            .filter(|context| !context.is_empty())
+    }
+
+    /// Returns the translation key without any `[]` suffix, and the type declared for its values.
+    fn key<'r>(
+        &self,
+        key: &'r str,
+        record: &StringRecord,
+    ) -> Result<(&'r str, Option<ValueType>), Error> {
+        let declared = match self.type_column.and_then(|idx| record.get(idx)) {
+            Some(value_type) if !value_type.trim().is_empty() => Some(value_type.parse()?),
+            _ => None,
+        };
+
+        match key.strip_suffix("[]") {
+            Some(stripped) => Ok((stripped, declared.or(Some(ValueType::Json)))),
+            None => Ok((key, declared)),
+        }
+    }
+}
+
+/// Returns an error if `value` isn't valid for the type declared for its key. Values of keys
+/// without a declared type, and empty values, are always valid.
+fn check_value(
+    value: &str,
+    value_type: Option<ValueType>,
+    key: &str,
+    locale: &str,
+    location: Location,
+) -> Result<(), Error> {
+    match value_type {
+        Some(ValueType::String | ValueType::Auto) | None => Ok(()),
+        Some(_) if value.is_empty() => Ok(()),
+        Some(value_type) => match value_type.to_json(value) {
+            Some(_) => Ok(()),
+            None => Err(Error::InvalidValue {
+                key: key.to_owned(),
+                locale: locale.to_owned(),
+                line: location.line,
+                value_type,
+            }),
+        },
+    }
+}
+
+/// Removes a byte order mark left at the start of the first heading, so an `id` heading exported
+/// with one is still recognised.
+fn strip_bom(headings: &StringRecord) -> Cow<'_, StringRecord> {
+    match headings.get(0) {
+        Some(first) if first.starts_with('\u{feff}') => Cow::Owned(
+            std::iter::once(first.trim_start_matches('\u{feff}'))
+                .chain(headings.iter().skip(1))
+                .collect(),
+        ),
+        _ => Cow::Borrowed(headings),
+    }
+}
+
+/// Convert CSV records into translations using structured deserialization, without writing any files.
 ///
-/// * `reader` - a configured CSV reader
+/// * `reader` - a configured CSV reader, or any other [`RecordSource`]
 /// * `headings` - heading row for the CSV file
-/// * `rows` - number of rows that are in the CSV file
 /// * `config` - parsed command line configuration
-pub fn generate_json(
-    reader: &mut Reader<File>,
+/// * `diagnostics` - receives duplicate key and empty value events
+pub fn convert<S: RecordSource + ?Sized>(
+    reader: &mut S,
     headings: &StringRecord,
-    rows: usize,
     config: &Config,
-) -> Result<(), std::io::Error> {
-    // HashMap::with_capacity_and_hasher(capacity, hasher) can be used instead, with hasher
-    // that is faster https://crates.io/keywords/hasher
-    let mut dictionary: HashMap<&str, Map<String, Value>> = HashMap::with_capacity(rows);
-    let mut times_overwritten = 0;
-
-    for (idx, item) in reader.deserialize().enumerate() {
-        let record: Translations = item?;
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Translations, Error> {
+    let headings = strip_bom(headings);
+    let headings = headings.as_ref();
+    let mut translations = Translations::default();
+    let mut key_lines = KeyLines::new(config.on_duplicate);
+    let mut row = StringRecord::new();
+    let mut records = 0;
+    let ignored_headings = if let Some(list) = &config.ignored_headings {
+        list.clone()
+    } else {
+        vec![""]
+    };
+
+    let key_columns = KeyColumns::new(config, headings);
+
+    while reader.read_record(&mut row)? {
+        let record: TranslationRecord = row.deserialize(Some(headings))?;
+        let location = Location::from(row.position());
+        let (key, value_type) = key_columns.key(&record.id, &row)?;
+        let key_idx = translations.add_key(key, location.line);
+        key_lines.add(key_idx, location.line);
+        if let Some(value_type) = value_type {
+            translations.set_value_type(key_idx, value_type);
+        }
+        if let Some(description) = key_columns.description(&row) {
+            translations.set_description(key_idx, description);
+        }
+        if let Some(context) = key_columns.context(&row) {
+            translations.set_context(key_idx, context);
+        }
         let mut overwrote_data = false;
-        let ignored_headings = if let Some(list) = &config.ignored_headings {
-            list.clone()
-        } else {
-            vec![""]
-        };
+        records += 1;
 
         // Loop in a loop? Incredibly inefficient? Who cares!? Optimize when it matters.
-        for heading in headings.iter() {
+        for (column_idx, heading) in headings.iter().enumerate() {
             let heading = heading.trim();
             // Only process for language headings
-            if heading != "id" && !ignored_headings.contains(&heading) && !heading.is_empty() {
-                let kv = record.format_lang(heading);
-                if let Some(lang_map) = dictionary.get_mut(heading) {
-                    // No matter what the parser thinks, we want everything treated as a string
-                    let value = match kv.1 {
-                        LangData::Float(v) => format!("{v}"),
-                        LangData::Integer(v) => format!("{v}"),
-                        LangData::String(v) => v.to_owned(),
-                    };
-                    // if the new value is empty and the old value is not empty, skip replacement.
-                    if let Some(old_val) = lang_map.get(kv.0) {
-                        if value.is_empty() && old_val != "" {
-                            continue;
-                        };
+            if heading != "id"
+                && !ignored_headings.contains(&heading)
+                && !heading.is_empty()
+                && !key_columns.is_key_column(column_idx)
+            {
+                let value = record.format_lang(heading)?.1;
+                check_value(value, value_type, key, heading, location)?;
+
+                if value.is_empty() {
+                    diagnostics.report(Diagnostic::EmptyValue {
+                        locale: heading.to_owned(),
+                        key: key.to_owned(),
+                        location,
+                    });
+                    if config.omit_empty {
+                        translations.add_locale(heading);
+                        continue;
                     }
+                }
 
-                    let old_val = lang_map.insert(kv.0.into(), value.into());
-                    if let Some(_val) = old_val {
-                        if !overwrote_data {
-                            println!(
-                                "{} key \"{}\" overwritten by record {} (line {}).",
-                                "Warning:".on_yellow().italic(),
-                                kv.0,
-                                idx + 1,
-                                idx + 1
-                            );
-                            overwrote_data = true;
-                            times_overwritten += 1;
-                        }
+                let replaced = translations.insert(heading, key_idx, value, config.on_duplicate);
+                if let Some(previous_line) = replaced.filter(|_| !overwrote_data) {
+                    let overwritten = OverwrittenKey {
+                        key: key.to_owned(),
+                        location,
+                        previous_line: Some(previous_line),
                     };
-                } else {
-                    dictionary.insert(heading, Map::with_capacity(rows));
-                    // No matter what the parser thinks, we want everything treated as a string
-                    let value = match kv.1 {
-                        LangData::Float(v) => format!("{v}"),
-                        LangData::Integer(v) => format!("{v}"),
-                        LangData::String(v) => v.to_owned(),
-                    };
-
-                    dictionary
-                        .get_mut(heading)
-                        .expect("Unexpected error after creating map")
-                        .insert(kv.0.into(), value.into());
+                    report_overwritten(&mut translations, diagnostics, overwritten);
+                    overwrote_data = true;
                 }
             }
         }
     }
 
-    if times_overwritten > 0 {
-        println!("\n{times_overwritten} {DUPE_KEY_NOTICE}")
-    }
+    key_lines.check(&translations, diagnostics)?;
+    diagnostics.report(Diagnostic::Converted {
+        records,
+        overwritten: translations.overwritten.len(),
+    });
 
-    for lang in dictionary.keys() {
-        let mut filename = get_file_location(config.output_dir)?;
-
-        if let Some(outfile) = config.output_filename {
-            filename.push(lang);
-            create_dir_all(&filename)?;
-            filename.push(format!("{outfile}.json"));
-        } else {
-            filename.push(format!("{lang}.json"));
-        }
-
-        if let Some(json) = dictionary.get(lang) {
-            writeln!(
-                File::create(&filename)?,
-                "{}",
-                to_string_pretty(json).expect("Error writing {lang}.json.")
-            )?;
-        }
-        println!(
-            "{} written to {}.",
-            filename.file_name().unwrap().to_string_lossy(),
-            filename.parent().unwrap().to_string_lossy()
-        );
-    }
-
-    Ok(())
+    Ok(translations)
 }
 
-/// Generate JSON files from CSV using StringRecord
+/// Convert CSV records into translations using StringRecord, without writing any files.
 ///
-/// * `reader` - a configured CSV reader
+/// * `reader` - a configured CSV reader, or any other [`RecordSource`]
 /// * `headings` - heading row for the CSV file
-/// * `rows` - number of rows that are in the CSV file
 /// * `config` - parsed command line configuration
-pub fn generate_json_fast(
-    reader: &mut Reader<File>,
+/// * `diagnostics` - receives duplicate key and empty value events
+pub fn convert_fast<S: RecordSource + ?Sized>(
+    reader: &mut S,
     headings: &StringRecord,
-    rows: usize,
     config: &Config,
-) -> Result<(), std::io::Error> {
-    // HashMap::with_capacity_and_hasher(capacity, hasher) can be used instead, with hasher
-    // that is faster https://crates.io/keywords/hasher
-    let mut dictionary: HashMap<&str, Map<String, Value>> = HashMap::with_capacity(rows);
-    let mut times_overwritten = 0;
-
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<Translations, Error> {
+    let headings = strip_bom(headings);
+    let headings = headings.as_ref();
+    let mut translations = Translations::default();
+    let mut key_lines = KeyLines::new(config.on_duplicate);
     let mut record = StringRecord::new();
-    let mut idx = 0;
+    let mut records = 0;
     let ignored_headings = if let Some(list) = &config.ignored_headings {
         list.clone()
     } else {
         vec![""]
     };
 
+    let key_columns = KeyColumns::new(config, headings);
+
     while reader.read_record(&mut record)? {
+        let location = Location::from(record.position());
+        let (key, value_type) = key_columns.key(&record[0], &record)?;
+        let key_idx = translations.add_key(key, location.line);
+        key_lines.add(key_idx, location.line);
+        if let Some(value_type) = value_type {
+            translations.set_value_type(key_idx, value_type);
+        }
+        if let Some(description) = key_columns.description(&record) {
+            translations.set_description(key_idx, description);
+        }
+        if let Some(context) = key_columns.context(&record) {
+            translations.set_context(key_idx, context);
+        }
         let mut overwrote_data = false;
-        idx += 1;
+        records += 1;
 
         // Loop in a loop? Incredibly inefficient? Who cares!? Optimize when it matters.
         for (column_idx, heading) in headings.iter().enumerate() {
             let heading = heading.trim();
             // Only process for language headings
-            if column_idx != 0 && !ignored_headings.contains(&heading) && !heading.is_empty() {
+            if column_idx != 0
+                && !ignored_headings.contains(&heading)
+                && !heading.is_empty()
+                && !key_columns.is_key_column(column_idx)
+            {
                 let value = match &record.get(column_idx) {
                     Some(head) => head,
                     None => "",
                 };
-
-                // Check if there's an existing translation key record in the language map
-                // and replace it.
-                if let Some(lang_map) = dictionary.get_mut(heading) {
-                    // But if the new value is empty and the old value is not empty, skip replacement.
-                    if let Some(old_val) = lang_map.get(&record[0]) {
-                        if value.is_empty() && old_val != "" {
-                            continue;
-                        };
+                check_value(value, value_type, key, heading, location)?;
+
+                if value.is_empty() {
+                    diagnostics.report(Diagnostic::EmptyValue {
+                        locale: heading.to_owned(),
+                        key: key.to_owned(),
+                        location,
+                    });
+                    if config.omit_empty {
+                        translations.add_locale(heading);
+                        continue;
                     }
+                }
 
-                    let old_val = lang_map.insert(record[0].into(), value.into());
-                    if let Some(_val) = old_val {
-                        if !overwrote_data {
-                            println!(
-                                "{} key \"{}\" overwritten by record {} (line {}).",
-                                "Warning:".on_yellow().italic(),
-                                &record[0],
-                                idx,
-                                idx
-                            );
-                            overwrote_data = true;
-                            times_overwritten += 1;
-                        }
-                    };
-                } else {
-                    dictionary.insert(heading, Map::with_capacity(rows));
-                    // No matter what the parser thinks, we want everything treated as a string
-                    let value = match &record.get(column_idx) {
-                        Some(head) => head,
-                        None => "",
+                let replaced = translations.insert(heading, key_idx, value, config.on_duplicate);
+                if let Some(previous_line) = replaced.filter(|_| !overwrote_data) {
+                    let overwritten = OverwrittenKey {
+                        key: key.to_owned(),
+                        location,
+                        previous_line: Some(previous_line),
                     };
-
-                    dictionary
-                        .get_mut(heading)
-                        .expect("Unexpected error after creating map")
-                        .insert(record[0].into(), value.into());
+                    report_overwritten(&mut translations, diagnostics, overwritten);
+                    overwrote_data = true;
                 }
             }
         }
     }
 
-    if times_overwritten > 0 {
-        println!("\n{times_overwritten} {DUPE_KEY_NOTICE}")
-    }
+    key_lines.check(&translations, diagnostics)?;
+    diagnostics.report(Diagnostic::Converted {
+        records,
+        overwritten: translations.overwritten.len(),
+    });
 
-    for lang in dictionary.keys() {
-        let mut filename = get_file_location(config.output_dir)?;
+    Ok(translations)
+}
 
-        if let Some(outfile) = config.output_filename {
-            filename.push(lang);
-            create_dir_all(&filename)?;
-            filename.push(format!("{outfile}.json"));
-        } else {
-            filename.push(format!("{lang}.json"));
-        }
+/// Records an overwritten translation key, and reports it as a duplicate key.
+fn report_overwritten(
+    translations: &mut Translations,
+    diagnostics: &mut dyn DiagnosticSink,
+    overwritten: OverwrittenKey,
+) {
+    diagnostics.report(Diagnostic::DuplicateKey(overwritten.clone()));
+    translations.overwritten.push(overwritten);
+}
 
-        if let Some(json) = dictionary.get(lang) {
-            writeln!(
-                File::create(&filename)?,
-                "{}",
-                to_string_pretty(json).expect("Error writing {lang}.json.")
-            )?;
-        }
-        println!(
-            "{} written to {}.",
-            filename.file_name().unwrap().to_string_lossy(),
-            filename.parent().unwrap().to_string_lossy()
-        );
+/// Writes each locale in the configured output format, reporting every file written.
+pub(crate) fn write_output(
+    translations: &Translations,
+    config: &Config,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    for file in config.format.writer().write(translations, config)? {
+        diagnostics.report(Diagnostic::FileWritten(file));
     }
 
     Ok(())
 }
 
+/// Generate JSON files from CSV using structured deserialization
+///
+/// Files are written in the configured [`OutputFormat`](crate::OutputFormat), JSON by default.
+///
+/// * `reader` - a configured CSV reader
+/// * `headings` - heading row for the CSV file
+/// * `config` - parsed command line configuration
+/// * `diagnostics` - receives conversion events and the location of each file written
+pub fn generate_json<S: RecordSource + ?Sized>(
+    reader: &mut S,
+    headings: &StringRecord,
+    config: &Config,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    let translations = convert(reader, headings, config, diagnostics)?;
+    write_output(&translations, config, diagnostics)
+}
+
+/// Generate JSON files from CSV using StringRecord
+///
+/// Files are written in the configured [`OutputFormat`](crate::OutputFormat), JSON by default.
+///
+/// * `reader` - a configured CSV reader
+/// * `headings` - heading row for the CSV file
+/// * `config` - parsed command line configuration
+/// * `diagnostics` - receives conversion events and the location of each file written
+pub fn generate_json_fast<S: RecordSource + ?Sized>(
+    reader: &mut S,
+    headings: &StringRecord,
+    config: &Config,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    let translations = convert_fast(reader, headings, config, diagnostics)?;
+    write_output(&translations, config, diagnostics)
+}
+
 #[cfg(test)]
 mod generator_tests {
-    use super::generate_json_fast;
-    use crate::{get_file_location, get_file_reader, Config};
-    use csv::{Reader, StringRecord, Terminator, Trim};
+    use super::{convert, convert_fast, generate_json_fast};
+    use crate::writers::WrittenFile;
+    use crate::{
+        get_file_location, get_file_reader, Config, Diagnostic, DuplicateKey, DuplicatePolicy,
+        Error, InputFile, Location, OverwrittenKey, ValueType,
+    };
+    use csv::{Reader, StringRecord, Trim};
     use pretty_assertions::assert_eq;
     use std::fs::{self, File};
     use std::io::Write;
     use std::path::Path;
 
-    const CONFIG: Config = Config {
-        delimiter: b',',
-        escape_char: b'"',
-        ignored_headings: None,
-        flexible: true,
-        output_dir: "",
-        output_filename: None,
-        terminator_char: Terminator::CRLF,
-        trim_whitespace: Trim::Fields,
-    };
+    fn test_config() -> Config<'static> {
+        Config {
+            trim_whitespace: Trim::Fields,
+            ..Config::default()
+        }
+    }
 
     const CSV_ALL_LANG: &str = "\
 id,da_DK,de_DE,en_US,es_ES,fr_FR,it_IT,LangDomain,nl_NL,pt_BR,pt_PT,sv_SE,
@@ -310,27 +504,24 @@ new.translation\tny oversættelse\t
         input_filename: &str,
         input_data: &str,
         config: &Config,
-    ) -> (Reader<File>, StringRecord, usize) {
+    ) -> (Reader<InputFile>, StringRecord) {
         File::options()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(input_filename)
             .unwrap()
             .write_all(input_data.as_bytes())
             .unwrap();
         let mut reader = get_file_reader(input_filename, config).unwrap();
-        let file = get_file_location(input_filename).unwrap();
-        let mut reader_count = Reader::from_path(file).unwrap();
-
         let headings = reader.headers().unwrap().clone();
-        let rows = reader_count.byte_records().count();
 
-        (reader, headings, rows)
+        (reader, headings)
     }
 
     #[test]
     fn it_writes_all_columns_except_langdomain_to_a_file() {
-        let test_file_path = "test_file0.csv";
+        let test_file_path = "test_file_all.csv";
         let illegal_file = "LangDomain.json";
         let lang_file_list = [
             "da_DK.json",
@@ -358,11 +549,11 @@ new.translation\tny oversættelse\t
         ];
         let config = &Config {
             ignored_headings: Some(vec!["LangDomain"]),
-            ..CONFIG
+            ..test_config()
         };
         let mut test_conf = generate_csv_reader(test_file_path, CSV_ALL_LANG, config);
 
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, config).unwrap();
+        generate_json_fast(&mut test_conf.0, &test_conf.1, config, &mut Vec::new()).unwrap();
 
         for (idx, file) in lang_file_list.iter().enumerate() {
             let trans = fs::read_to_string(file).unwrap();
@@ -384,11 +575,11 @@ new.translation\tny oversættelse\t
         let lang_file_path = "da_DK_t.json";
         let config = &Config {
             delimiter: b'\t',
-            ..CONFIG
+            ..test_config()
         };
         let mut test_conf = generate_csv_reader(test_file_path, TSV_ROW_1, config);
 
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, config).unwrap();
+        generate_json_fast(&mut test_conf.0, &test_conf.1, config, &mut Vec::new()).unwrap();
 
         let trans = fs::read_to_string(lang_file_path).unwrap();
         fs::remove_file(test_file_path).unwrap();
@@ -403,11 +594,11 @@ new.translation\tny oversættelse\t
         let lang_file_path = "da_DK_a/locales.json";
         let config = &Config {
             output_filename: Some("locales"),
-            ..CONFIG
+            ..test_config()
         };
 
         let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_A, config);
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, config).unwrap();
+        generate_json_fast(&mut test_conf.0, &test_conf.1, config, &mut Vec::new()).unwrap();
 
         assert!(Path::new(lang_file_path).exists());
         fs::remove_file(lang_file_path).unwrap();
@@ -415,11 +606,11 @@ new.translation\tny oversættelse\t
         let config = &Config {
             output_dir: "custom",
             output_filename: Some("locales"),
-            ..CONFIG
+            ..test_config()
         };
         let lang_file_path = "custom/da_DK_a/locales.json";
         let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_A, config);
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, config).unwrap();
+        generate_json_fast(&mut test_conf.0, &test_conf.1, config, &mut Vec::new()).unwrap();
 
         assert!(Path::new(lang_file_path).exists());
 
@@ -434,11 +625,11 @@ new.translation\tny oversættelse\t
         let lang_file_path = "da_DK_s.json";
         let config = &Config {
             delimiter: b';',
-            ..CONFIG
+            ..test_config()
         };
         let mut test_conf = generate_csv_reader(test_file_path, SSV_ROW_1, config);
 
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, config).unwrap();
+        generate_json_fast(&mut test_conf.0, &test_conf.1, config, &mut Vec::new()).unwrap();
 
         let trans = fs::read_to_string(lang_file_path).unwrap();
         fs::remove_file(test_file_path).unwrap();
@@ -454,11 +645,11 @@ new.translation\tny oversættelse\t
         let lang_file_0 = "da_DK_0.json";
         let config = &Config {
             ignored_headings: Some(vec!["da_DK_0"]),
-            ..CONFIG
+            ..test_config()
         };
 
         let mut test_conf_0 = generate_csv_reader(test_file_0, CSV_ROW_0, config);
-        generate_json_fast(&mut test_conf_0.0, &test_conf_0.1, test_conf_0.2, config).unwrap();
+        generate_json_fast(&mut test_conf_0.0, &test_conf_0.1, config, &mut Vec::new()).unwrap();
 
         fs::remove_file(test_file_0).unwrap();
         assert!(File::open(lang_file_0).is_err());
@@ -469,14 +660,26 @@ new.translation\tny oversættelse\t
         // Empty translation
         let test_file_0 = "test_file0.csv";
         let lang_file_0 = "da_DK_0.json";
-        let mut test_conf_0 = generate_csv_reader(test_file_0, CSV_ROW_0, &CONFIG);
-        generate_json_fast(&mut test_conf_0.0, &test_conf_0.1, test_conf_0.2, &CONFIG).unwrap();
+        let mut test_conf_0 = generate_csv_reader(test_file_0, CSV_ROW_0, &test_config());
+        generate_json_fast(
+            &mut test_conf_0.0,
+            &test_conf_0.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         // Actual translation
         let test_file_1 = "test_file1.csv";
         let lang_file_1 = "da_DK_1.json";
-        let mut test_conf_1 = generate_csv_reader(test_file_1, CSV_ROW_1, &CONFIG);
-        generate_json_fast(&mut test_conf_1.0, &test_conf_1.1, test_conf_1.2, &CONFIG).unwrap();
+        let mut test_conf_1 = generate_csv_reader(test_file_1, CSV_ROW_1, &test_config());
+        generate_json_fast(
+            &mut test_conf_1.0,
+            &test_conf_1.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         let trans_0 = fs::read_to_string(lang_file_0).unwrap();
         fs::remove_file(test_file_0).unwrap();
@@ -494,9 +697,15 @@ new.translation\tny oversættelse\t
     fn it_does_not_overwrite_existing_key_with_empty_value() {
         let test_file_path = "test_file2.csv";
         let lang_file_path = "da_DK_2.json";
-        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_2, &CONFIG);
+        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_2, &test_config());
 
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, &CONFIG).unwrap();
+        generate_json_fast(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         let trans = fs::read_to_string(lang_file_path).unwrap();
         fs::remove_file(test_file_path).unwrap();
@@ -509,9 +718,15 @@ new.translation\tny oversættelse\t
     fn it_overwrites_existing_key_with_new_value() {
         let test_file_path = "test_file3.csv";
         let lang_file_path = "da_DK_3.json";
-        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_3, &CONFIG);
+        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_3, &test_config());
 
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, &CONFIG).unwrap();
+        generate_json_fast(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         let trans = fs::read_to_string(lang_file_path).unwrap();
         fs::remove_file(test_file_path).unwrap();
@@ -524,9 +739,15 @@ new.translation\tny oversættelse\t
     fn it_overwrites_empty_value_with_new_value() {
         let test_file_path = "test_file4.csv";
         let lang_file_path = "da_DK_4.json";
-        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_4, &CONFIG);
+        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_4, &test_config());
 
-        generate_json_fast(&mut test_conf.0, &test_conf.1, test_conf.2, &CONFIG).unwrap();
+        generate_json_fast(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         let trans = fs::read_to_string(lang_file_path).unwrap();
         fs::remove_file(test_file_path).unwrap();
@@ -534,4 +755,355 @@ new.translation\tny oversættelse\t
 
         assert_eq!(trans, DA_JSON_1);
     }
+
+    #[test]
+    fn it_converts_to_translations_without_writing_files() {
+        let test_file_path = "test_file_convert.csv";
+        let lang_file_path = "da_DK_c.json";
+        let mut test_conf = generate_csv_reader(
+            test_file_path,
+            &CSV_ROW_3.replace("da_DK_3", "da_DK_c"),
+            &test_config(),
+        );
+
+        let translations = convert_fast(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        fs::remove_file(test_file_path).unwrap();
+
+        assert!(!Path::new(lang_file_path).exists());
+        assert_eq!(
+            translations.get("da_DK_c", "new.translation").unwrap(),
+            "nyoversættelse"
+        );
+        assert_eq!(
+            translations.overwritten,
+            vec![OverwrittenKey {
+                key: "new.translation".into(),
+                location: Location {
+                    record: 3,
+                    line: 4,
+                    byte: 64
+                },
+                previous_line: Some(2)
+            }]
+        );
+    }
+
+    #[test]
+    fn it_returns_an_error_for_unknown_locales_in_strict_mode() {
+        let test_file_path = "test_file_unknown.csv";
+        let mut test_conf = generate_csv_reader(test_file_path, CSV_ALL_LANG, &test_config());
+
+        let result = convert(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut Vec::new(),
+        );
+        fs::remove_file(test_file_path).unwrap();
+
+        assert!(matches!(result, Err(Error::UnknownLocale(locale)) if locale == "LangDomain"));
+    }
+
+    #[test]
+    fn it_reports_diagnostics_to_the_sink() {
+        let test_file_path = "test_file_diagnostics.csv";
+        let lang_file_path = "da_DK_d.json";
+        let mut diagnostics = Vec::new();
+        let mut test_conf = generate_csv_reader(
+            test_file_path,
+            &CSV_ROW_3.replace("da_DK_3", "da_DK_d"),
+            &test_config(),
+        );
+
+        generate_json_fast(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut diagnostics,
+        )
+        .unwrap();
+        fs::remove_file(test_file_path).unwrap();
+        fs::remove_file(lang_file_path).unwrap();
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic::EmptyValue {
+                    locale: "da_DK_d".into(),
+                    key: "new.translation".into(),
+                    location: Location {
+                        record: 2,
+                        line: 3,
+                        byte: 46
+                    }
+                },
+                Diagnostic::DuplicateKey(OverwrittenKey {
+                    key: "new.translation".into(),
+                    location: Location {
+                        record: 3,
+                        line: 4,
+                        byte: 64
+                    },
+                    previous_line: Some(2)
+                }),
+                Diagnostic::DuplicateKeys(vec![DuplicateKey {
+                    key: "new.translation".into(),
+                    lines: vec![2, 3, 4]
+                }]),
+                Diagnostic::Converted {
+                    records: 3,
+                    overwritten: 1
+                },
+                Diagnostic::FileWritten(WrittenFile {
+                    path: get_file_location(lang_file_path).unwrap(),
+                    locale: Some("da_DK_d".into()),
+                    keys: 1,
+                    omitted: 0
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_keeps_values_according_to_the_duplicate_policy() {
+        let policies = [
+            (DuplicatePolicy::LastWins, "nyoversættelse"),
+            (DuplicatePolicy::FirstWins, "ny oversættelse"),
+            (DuplicatePolicy::KeepLongest, "ny oversættelse"),
+        ];
+
+        for (idx, (policy, expected)) in policies.into_iter().enumerate() {
+            let test_file_path = format!("test_file_policy_{idx}.csv");
+            let config = &Config {
+                on_duplicate: policy,
+                ..test_config()
+            };
+            let mut test_conf = generate_csv_reader(&test_file_path, CSV_ROW_3, config);
+            let mut diagnostics = Vec::new();
+
+            let translations =
+                convert_fast(&mut test_conf.0, &test_conf.1, config, &mut diagnostics).unwrap();
+            fs::remove_file(test_file_path).unwrap();
+
+            assert_eq!(
+                translations.get("da_DK_3", "new.translation").unwrap(),
+                expected
+            );
+            assert!(
+                diagnostics.contains(&Diagnostic::DuplicateKeys(vec![DuplicateKey {
+                    key: "new.translation".into(),
+                    lines: vec![2, 3, 4]
+                }]))
+            );
+        }
+    }
+
+    #[test]
+    fn it_lists_every_duplicate_key_when_duplicates_are_an_error() {
+        let test_file_path = "test_file_policy_error.csv";
+        let config = &Config {
+            on_duplicate: DuplicatePolicy::Error,
+            ..test_config()
+        };
+        let mut test_conf = generate_csv_reader(test_file_path, CSV_ROW_3, config);
+
+        let result = convert_fast(&mut test_conf.0, &test_conf.1, config, &mut Vec::new());
+        fs::remove_file(test_file_path).unwrap();
+
+        match result {
+            Err(Error::DuplicateKeys(keys)) => assert_eq!(
+                keys,
+                vec![DuplicateKey {
+                    key: "new.translation".into(),
+                    lines: vec![2, 3, 4]
+                }]
+            ),
+            _ => panic!("expected duplicate keys error"),
+        }
+    }
+
+    #[test]
+    fn it_reports_source_lines_of_records_with_multi_line_cells() {
+        let test_file_path = "test_file_lines.csv";
+        let input = "id,da_DK_m,\nfirst,\"line one\nline two\",\nsecond,,\nfirst,ny,\n";
+        let mut test_conf = generate_csv_reader(test_file_path, input, &test_config());
+
+        let translations = convert_fast(
+            &mut test_conf.0,
+            &test_conf.1,
+            &test_config(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        fs::remove_file(test_file_path).unwrap();
+
+        assert_eq!(
+            translations.overwritten,
+            vec![OverwrittenKey {
+                key: "first".into(),
+                location: Location {
+                    record: 3,
+                    line: 5,
+                    byte: 48
+                },
+                previous_line: Some(2)
+            }]
+        );
+    }
+
+    #[test]
+    fn it_strips_a_byte_order_mark_from_the_first_heading_in_both_generators() {
+        let headings = "\u{feff}id,da_DK,de_DE,en_US,es_ES,fr_FR,it_IT,nl_NL,pt_BR,pt_PT,sv_SE"
+            .split(',')
+            .collect::<StringRecord>();
+        let data = "app.title,a,b,Hello,d,e,f,g,h,i,j\n";
+
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+        let strict = convert(&mut reader, &headings, &test_config(), &mut Vec::new()).unwrap();
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+        let fast = convert_fast(&mut reader, &headings, &test_config(), &mut Vec::new()).unwrap();
+
+        assert_eq!(strict.get("en_US", "app.title"), Some("Hello"));
+        assert_eq!(fast.get("en_US", "app.title"), Some("Hello"));
+        assert!(fast.locale("\u{feff}id").is_none());
+    }
+
+    #[test]
+    fn it_keeps_the_exact_text_of_numeric_cells_in_strict_mode() {
+        let headings = "id,da_DK,de_DE,en_US,es_ES,fr_FR,it_IT,nl_NL,pt_BR,pt_PT,sv_SE"
+            .split(',')
+            .collect::<StringRecord>();
+        let data = "price,1.50,007,12345678901234567890,2e3,-0,a,b,c,d,e\n";
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+
+        let translations =
+            convert(&mut reader, &headings, &test_config(), &mut Vec::new()).unwrap();
+
+        assert_eq!(translations.get("da_DK", "price"), Some("1.50"));
+        assert_eq!(translations.get("de_DE", "price"), Some("007"));
+        assert_eq!(
+            translations.get("en_US", "price"),
+            Some("12345678901234567890")
+        );
+        assert_eq!(translations.get("es_ES", "price"), Some("2e3"));
+        assert_eq!(translations.get("fr_FR", "price"), Some("-0"));
+    }
+
+    #[test]
+    fn it_declares_value_types_by_key_suffix_or_type_column() {
+        let input = "id,type,en_US\nmonths[],,\"[\"\"Jan\"\", \"\"Feb\"\"]\"\nlimit,number,10\nbeta,boolean,\n";
+        let config = Config {
+            type_column: Some("type"),
+            ..test_config()
+        };
+        let mut test_conf = generate_csv_reader("test_file_value_types.csv", input, &config);
+
+        let translations =
+            convert_fast(&mut test_conf.0, &test_conf.1, &config, &mut Vec::new()).unwrap();
+        fs::remove_file("test_file_value_types.csv").unwrap();
+
+        assert_eq!(
+            translations.get("en_US", "months"),
+            Some("[\"Jan\", \"Feb\"]")
+        );
+        assert_eq!(translations.value_type("months"), Some(ValueType::Json));
+        assert_eq!(translations.value_type("limit"), Some(ValueType::Number));
+        assert_eq!(translations.value_type("beta"), Some(ValueType::Boolean));
+        assert!(translations.locale("type").is_none());
+    }
+
+    #[test]
+    fn it_returns_an_error_for_values_not_matching_their_declared_type() {
+        let input = "id,type,en_US\nlimit,number,ten\n";
+        let config = Config {
+            type_column: Some("type"),
+            ..test_config()
+        };
+        let mut test_conf = generate_csv_reader("test_file_invalid_value.csv", input, &config);
+
+        let result = convert_fast(&mut test_conf.0, &test_conf.1, &config, &mut Vec::new());
+        fs::remove_file("test_file_invalid_value.csv").unwrap();
+
+        assert!(matches!(
+            result,
+            Err(Error::InvalidValue {
+                line: 2,
+                value_type: ValueType::Number,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn it_omits_empty_values_when_configured_to_do_so() {
+        let input =
+            "id,en_US,fr_FR,de_DE\napp.title,Hello,,\napp.title,,Bonjour,\napp.close,Close,,\n";
+        let config = Config {
+            omit_empty: true,
+            ..test_config()
+        };
+        let mut test_conf = generate_csv_reader("test_file_omit_empty.csv", input, &config);
+
+        let translations =
+            convert_fast(&mut test_conf.0, &test_conf.1, &config, &mut Vec::new()).unwrap();
+        fs::remove_file("test_file_omit_empty.csv").unwrap();
+
+        let fr_fr = translations.locale("fr_FR").unwrap();
+        let de_de = translations.locale("de_DE").unwrap();
+        assert_eq!(fr_fr.iter().collect::<Vec<_>>(), [("app.title", "Bonjour")]);
+        assert_eq!(fr_fr.omitted(), 1);
+        assert_eq!(de_de.len(), 0);
+        assert_eq!(de_de.omitted(), 2);
+        assert_eq!(translations.get("en_US", "app.title"), Some("Hello"));
+    }
+
+    #[test]
+    fn it_reads_key_descriptions_from_the_description_column() {
+        let input =
+            "id,en_US,notes,fr_FR\napp.title,Hello,Window title,Bonjour\napp.close,Close,,Fermer\n";
+        let config = Config {
+            description_column: Some("notes"),
+            ..test_config()
+        };
+        let mut test_conf = generate_csv_reader("test_file_descriptions.csv", input, &config);
+
+        let translations =
+            convert_fast(&mut test_conf.0, &test_conf.1, &config, &mut Vec::new()).unwrap();
+        fs::remove_file("test_file_descriptions.csv").unwrap();
+
+        assert_eq!(translations.description("app.title"), Some("Window title"));
+        assert_eq!(translations.description("app.close"), None);
+        assert_eq!(translations.get("fr_FR", "app.title"), Some("Bonjour"));
+        assert!(translations.locale("notes").is_none());
+    }
+
+    #[test]
+    fn it_reads_key_contexts_from_the_context_column() {
+        let input = "id,class,en_US\nsave,Editor,Save\nquit,,Quit\n";
+        let config = Config {
+            context_column: Some("class"),
+            ..test_config()
+        };
+        let mut test_conf = generate_csv_reader("test_file_contexts.csv", input, &config);
+
+        let translations =
+            convert_fast(&mut test_conf.0, &test_conf.1, &config, &mut Vec::new()).unwrap();
+        fs::remove_file("test_file_contexts.csv").unwrap();
+
+        assert_eq!(translations.context("save"), Some("Editor"));
+        assert_eq!(translations.context("quit"), None);
+        assert!(translations.locale("class").is_none());
+    }
 }
@@ -23,26 +23,83 @@
 //!   "app.title": "Translocate means to move from one place to another."
 //! }
 //! ```
-
+//!
+//! ## Library use
+//! [`convert_fast`] and [`convert`] read an input file into [`Translations`] without any side
+//! effects, which can then be inspected or output by any [`TranslationWriter`], such as
+//! [`JsonWriter`]. [`run`] does both. Warnings and progress are reported as [`Diagnostic`]
+//! events to a [`DiagnosticSink`], rather than printed, so callers can collect or render them.
+
+mod diagnostics;
+mod dialect;
+mod encoding;
+mod error;
 mod generators;
+mod report;
+#[cfg(feature = "spreadsheet")]
+mod spreadsheet;
 mod translations;
+mod values;
+pub mod writers;
 
 use argh::FromArgs;
 use csv::{Reader, ReaderBuilder, StringRecord, Terminator, Trim};
-pub use generators::{generate_json, generate_json_fast};
-use std::{ffi::OsStr, fs, io, path::PathBuf};
-use yansi::Paint;
+pub use diagnostics::{Diagnostic, DiagnosticSink};
+pub use dialect::{Dialect, LineEnding};
+pub use encoding::InputFile;
+use encoding_rs::Encoding;
+pub use error::Error;
+pub use generators::{convert, convert_fast, generate_json, generate_json_fast, RecordSource};
+pub use report::{Report, ReportDialect, ReportDuplicate, ReportEmptyValue, ReportFile};
+#[cfg(feature = "spreadsheet")]
+pub use spreadsheet::{read_sheets, run_spreadsheet, Sheet, SheetRecords};
+use std::{ffi::OsStr, io, path::PathBuf, str::FromStr};
+pub use translations::{DuplicateKey, LocaleTranslations, Location, OverwrittenKey, Translations};
+pub use values::ValueType;
+pub use writers::{
+    ArbWriter, FluentWriter, JsonWriter, OutputFormat, PropertiesWriter, QtWriter, ResxWriter,
+    TranslationWriter, WebExtensionWriter, WrittenFile, XcstringsWriter,
+};
+
+/// File extensions of the spreadsheet formats which can be read with the `spreadsheet` feature.
+const SPREADSHEET_EXTENSIONS: [&str; 6] = ["xlsx", "xlsm", "xlsb", "xla", "xls", "ods"];
 
 #[derive(FromArgs)]
 #[argh(description = "High performance CSV translation to JSON translation file transformer.")]
 /// Defines the command line arguments that the translocate binary will accept.
 pub struct CliArgs {
+    #[argh(switch)]
+    /// convert every sheet of a spreadsheet input file, writing each sheet to a file named after it
+    /// inside a directory for each locale. It can't be used with the `webextension` format.
+    pub all_sheets: bool,
+    #[argh(option)]
+    /// name of a column holding the context of each key, such as the class using it. It isn't written as a
+    /// locale, and `qt` files group keys into `<context>` elements by it, rather than by the key before its first `.`.
+    pub context_column: Option<String>,
     #[argh(option, short = 'd')]
-    /// column delimiter to use when parsing. Uses `\t` for TSV and `,` for CSV by default.
+    /// column delimiter to use when parsing, as a single ASCII character, an escape sequence like `\t`, or a name
+    /// like `tab` or `semicolon`. Uses `\t` for TSV by default, and is otherwise detected from the start of the
+    /// file, falling back to `,`.
     pub delimiter: Option<String>,
+    #[argh(option)]
+    /// name of a column describing each key for translators. It isn't written as a locale, and is written as
+    /// the comment of each key in formats which have them, such as `resx`.
+    pub description_column: Option<String>,
+    #[argh(option)]
+    /// character encoding of the input file, such as `utf-8`, `utf-16le` or `windows-1252`. Detected from the start
+    /// of the file by default, with files which aren't UTF-8 or UTF-16 assumed to be `windows-1252`.
+    pub encoding: Option<String>,
     #[argh(option, short = 'e')]
-    /// escape character to use for quotes when parsing. Uses `\` for TSV and `"` for CSV by default.
+    /// escape character to use for quotes when parsing, given like the delimiter. Uses `\` for TSV and `"` for CSV
+    /// by default.
     pub escape_char: Option<String>,
+    #[argh(option, short = 'f')]
+    /// output format. One of `json` (default), `properties` for Java `messages_<locale>.properties` files, `resx`
+    /// for .NET `Strings.<culture>.resx` files, `arb` for Flutter `app_<locale>.arb` files, `fluent` for Project
+    /// Fluent `<locale>.ftl` files, `webextension` for browser extension `_locales/<locale>/messages.json` files,
+    /// `xcstrings` for a single Xcode `Localizable.xcstrings` catalog of every locale, or `qt` for Qt Linguist
+    /// `app_<locale>.ts` files.
+    pub format: Option<String>,
     #[argh(option, short = 'I')]
     /// list of csv column header names, separated by commas, to skip when converting to JSON.
     pub ignored_headings: Option<String>,
@@ -50,55 +107,198 @@ pub struct CliArgs {
     /// set whether input file parsing should continue if the number of columns in each record differs.
     /// Passing this flag enables stricter (less flexible) parsing.
     pub inflexible: bool,
+    #[argh(switch)]
+    /// leave keys with an empty value out of that locale's output, instead of writing an empty string, so
+    /// i18n libraries fall back to the default language. The number of keys left out is printed for each locale.
+    pub omit_empty: bool,
+    #[argh(option)]
+    /// how to handle a translation key which appears in more than one record. One of `last-wins` (default),
+    /// `first-wins`, `keep-longest` or `error`. Empty values never replace non-empty ones, and every duplicated key is
+    /// reported with the lines it appears on.
+    pub on_duplicate: Option<String>,
     #[argh(option, short = 'o')]
     /// desired output directory, if different from the current directory. Can be either a relative or absolute file path.
     pub output_dir: Option<String>,
     #[argh(option, short = 'O')]
     /// if set, saves each file with the name provided inside a directory named by the locale.
     pub output_filename: Option<String>,
+    #[argh(switch)]
+    /// write `.properties` files in UTF-8, instead of ISO-8859-1 with other characters escaped as `\uXXXX`.
+    /// Java 9 and newer read resource bundles as UTF-8.
+    pub properties_utf8: bool,
+    #[argh(option)]
+    /// output a machine readable summary of the conversion to stdout instead of progress messages.
+    /// The only supported format is `json`.
+    pub report: Option<String>,
+    #[argh(option)]
+    /// write a JSON summary of the conversion to the given file path. Progress messages are still printed.
+    pub report_file: Option<String>,
+    #[argh(option)]
+    /// name, or 1-based position, of the sheet to convert from a spreadsheet input file. The first sheet is used by default.
+    pub sheet: Option<String>,
+    #[argh(option)]
+    /// locale column holding the source text translations are made from, which is the first locale column by
+    /// default. Placeholders in `arb` output are found in the source locale's values, and `webextension` placeholders
+    /// are numbered in the order they appear in them. `qt` output uses its values as source text. It is also the
+    /// source language of `xcstrings` catalogs.
+    pub source_locale: Option<String>,
     #[argh(option, short = 't')]
-    /// character indicating end of each record, passed in as its decimal representation e.g. \n = 10, \r = 13, \t = 9.
-    /// CSV default is `\r`, `\n` OR `\r\n`. TSV default is `\n`.
+    /// character indicating end of each record, passed in as its decimal representation below 128 e.g. \n = 10, \r = 13, \t = 9,
+    /// like the delimiter e.g. `\n` or `pipe`, or `crlf` for any of `\r`, `\n` or `\r\n`.
+    /// CSV default is `\r`, `\n` OR `\r\n`. TSV default is `\n`, or any of them if the file is detected to use `\r\n` or `\r`.
     pub terminator: Option<String>,
     #[argh(switch, short = 'T')]
     /// trim leading and trailing whitespace in non-header columns. Enabled by passing this flag.
     pub trim: Option<bool>,
+    #[argh(option)]
+    /// name of a column declaring the type of each record's values: `string`, `number`, `boolean`, `null`, `json`
+    /// (also `array` or `object`) or `auto`. The column isn't written as a locale.
+    pub type_column: Option<String>,
+    #[argh(option)]
+    /// list of column header names, separated by commas, whose values are written as JSON numbers, booleans, null,
+    /// arrays or objects when they contain one.
+    pub typed_columns: Option<String>,
+    #[argh(switch)]
+    /// write values which are JSON numbers, booleans, null, arrays or objects as typed JSON values in every column,
+    /// instead of as strings. Numbers keep their exact text. Keys ending in `[]` are always parsed as JSON, without
+    /// the suffix, with or without this flag.
+    pub typed_values: bool,
     #[argh(switch, short = 'v')]
     /// version information
     pub version: Option<bool>,
     #[argh(positional)]
     #[argh(default = "String::from(\"translations.csv\")")]
-    /// relative or absolute path to CSV or TSV. If no file is provided, one called "translations.csv"
+    /// relative or absolute path to CSV, TSV, or a spreadsheet (xlsx, xls, ods) when built with the `spreadsheet`
+    /// feature. If no file is provided, one called "translations.csv"
     /// is looked for in the current directory.
     pub file: String,
 }
 
+/// Decides which value is kept when a translation key appears in more than one record.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Later non-empty values replace earlier ones.
+    #[default]
+    LastWins,
+    /// The first non-empty value is kept.
+    FirstWins,
+    /// Conversion fails, listing every duplicated key and the records it appears in.
+    Error,
+    /// The longest value is kept.
+    KeepLongest,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = Error;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "last-wins" => Ok(DuplicatePolicy::LastWins),
+            "first-wins" => Ok(DuplicatePolicy::FirstWins),
+            "error" => Ok(DuplicatePolicy::Error),
+            "keep-longest" => Ok(DuplicatePolicy::KeepLongest),
+            _ => Err(Error::InvalidConfig(format!("unknown duplicate key policy \"{policy}\". Use one of `last-wins`, `first-wins`, `keep-longest` or `error`."))),
+        }
+    }
+}
+
 /// Configures how the CSV file will be read. Defaults are modified after parsing any provided command line options
+#[derive(Clone)]
 pub struct Config<'a> {
     /// Delimiter character to use when separating columns. Uses `\t` for TSV and `,` for CSV by default.
     pub delimiter: u8,
+    /// Column describing each key for translators, written as comments by formats which support them.
+    pub description_column: Option<&'a str>,
+    /// Column naming the context of each key, which formats such as Qt Linguist group keys by.
+    pub context_column: Option<&'a str>,
+    /// Locale column holding the source text, which formats such as ARB read placeholders from.
+    pub source_locale: Option<&'a str>,
+    /// Flag to detect the delimiter and quote character from the input file, set when no delimiter was configured.
+    pub detect_delimiter: bool,
+    /// Flag to detect the line ending from the input file, set when no terminator was configured.
+    pub detect_terminator: bool,
+    /// Flag to use the detected quote character as the escape character, set when no escape character was configured.
+    pub detect_escape: bool,
+    /// Character encoding of the input file. Detected when reading the file if `None`.
+    pub encoding: Option<&'static Encoding>,
     /// Escape character to use for quotes when parsing columns. Uses `\` for TSV and `"` for CSV by default.
     pub escape_char: u8,
+    /// Quote character wrapping columns which contain delimiters or line endings. Default is `"`.
+    pub quote: u8,
     /// list of colum names, separated by commas, to skip when converting to JSON.
     pub ignored_headings: Option<Vec<&'a str>>,
     /// Flag to determine whether processing the input file should continue if the number of columns in records is not always the same.
     /// If true, parsing is less strict. Default is true.
     pub flexible: bool,
+    /// Flag to leave keys with an empty value out of a locale's output, rather than writing an empty string.
+    pub omit_empty: bool,
+    /// Decides which value is kept when a translation key appears in more than one record.
+    pub on_duplicate: DuplicatePolicy,
+    /// Format the converted translations are written in.
+    pub format: OutputFormat,
     /// Desired output directory, if different from the current directory. Can be either a relative or absolute file path.
     pub output_dir: &'a str,
     /// Unified filename for each localization file. Saves each file inside a directory named by the locale.
     pub output_filename: Option<&'a str>,
+    /// Flag to write `.properties` files in UTF-8, rather than ISO-8859-1 with `\uXXXX` escapes.
+    pub properties_utf8: bool,
+    /// Name, or 1-based position, of the spreadsheet sheet to convert. The first sheet is used by default.
+    pub sheet: Option<&'a str>,
+    /// Flag to convert every sheet of a spreadsheet, using each sheet name as the output filename.
+    pub all_sheets: bool,
+    /// Flag set when the input file is a spreadsheet rather than CSV or TSV, based on its extension.
+    pub spreadsheet: bool,
     /// Record terminator to use. CSV default is `\r`, `\n` or `\r\n`. TSV default is `\n`.
     pub terminator_char: Terminator,
     /// Flag to determine if non-header columns should be trimmed. Trims leading and trailing whitespace if enabled.
     pub trim_whitespace: Trim,
+    /// Name of the column declaring the [`ValueType`] of each record's values.
+    pub type_column: Option<&'a str>,
+    /// Columns whose values are written as typed JSON values when they contain one.
+    pub typed_columns: Option<Vec<&'a str>>,
+    /// Flag to write values in every column as typed JSON values when they contain one, instead of strings.
+    pub typed_values: bool,
+}
+
+/// Returns the configuration used for a CSV file when no command line options are given.
+impl Default for Config<'_> {
+    fn default() -> Self {
+        Config {
+            delimiter: b',',
+            description_column: None,
+            context_column: None,
+            source_locale: None,
+            detect_delimiter: true,
+            detect_terminator: true,
+            detect_escape: true,
+            encoding: None,
+            escape_char: b'"',
+            quote: b'"',
+            ignored_headings: None,
+            flexible: true,
+            omit_empty: false,
+            on_duplicate: DuplicatePolicy::default(),
+            format: OutputFormat::default(),
+            output_dir: "",
+            output_filename: None,
+            properties_utf8: false,
+            sheet: None,
+            all_sheets: false,
+            spreadsheet: false,
+            terminator_char: Terminator::CRLF,
+            trim_whitespace: Trim::None,
+            type_column: None,
+            typed_columns: None,
+            typed_values: false,
+        }
+    }
 }
 
 #[doc(hidden)]
 impl<'a> Config<'a> {
     /// Parses provided command line arguments and returns a configuration struct
     /// whose options determine how the input CSV file is read or processed.
-    pub fn new(args: &'a CliArgs, file_extension: Option<&OsStr>) -> Config<'a> {
+    pub fn new(args: &'a CliArgs, file_extension: Option<&OsStr>) -> Result<Config<'a>, Error> {
         let is_tsv = if let Some(val) = file_extension {
             val == "tsv"
         } else {
@@ -106,16 +306,35 @@ impl<'a> Config<'a> {
             false
         };
 
+        let spreadsheet = file_extension.and_then(OsStr::to_str).map_or(false, |ext| {
+            SPREADSHEET_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        });
+
+        if spreadsheet && !cfg!(feature = "spreadsheet") {
+            return Err(Error::InvalidConfig(String::from("spreadsheet input requires translocate to be built with the `spreadsheet` feature. Export the sheet to CSV, or reinstall with `--features spreadsheet`.")));
+        }
+
+        if args.all_sheets && args.output_filename.is_some() {
+            return Err(Error::InvalidConfig(String::from("`--all-sheets` names each output file after its sheet, so it can't be used with `--output-filename`.")));
+        }
+
         let delimiter = if let Some(delim) = &args.delimiter {
-            delim.as_bytes()[0]
+            parse_byte_option("delimiter", delim)?
         } else if is_tsv {
             b'\t'
         } else {
             b','
         };
 
+        let encoding = match &args.encoding {
+            Some(label) => Some(encoding::parse_encoding(label)?),
+            None => None,
+        };
+
         let escape_char = if let Some(esc) = &args.escape_char {
-            esc.as_bytes()[0]
+            parse_byte_option("escape character", esc)?
         } else if is_tsv {
             b'\\'
         } else {
@@ -129,6 +348,20 @@ impl<'a> Config<'a> {
             None
         };
 
+        let on_duplicate = match &args.on_duplicate {
+            Some(policy) => policy.parse()?,
+            None => DuplicatePolicy::default(),
+        };
+
+        let format = match &args.format {
+            Some(format) => format.parse()?,
+            None => OutputFormat::default(),
+        };
+
+        if args.all_sheets && format == OutputFormat::WebExtension {
+            return Err(Error::InvalidConfig(String::from("`--all-sheets` writes each sheet to its own file, but browsers only read one `messages.json` per locale, so it can't be used with `--format webextension`.")));
+        }
+
         let output_dir = if let Some(path) = &args.output_dir {
             path
         } else {
@@ -136,14 +369,18 @@ impl<'a> Config<'a> {
         };
 
         let output_filename = args.output_filename.as_deref();
+        let sheet = args.sheet.as_deref();
 
         let terminator_char = if let Some(terminate_on) = &args.terminator {
             match terminate_on.parse::<u8>() {
-                Ok(val) => Terminator::Any(val),
-                Err(err) => {
-                    eprintln!("{} {}. Try passing the decimal number (less than 256) representing your ascii separator.", "Error:".bold().on_bright_red(), err);
-                    std::process::exit(1)
+                Ok(val) if val.is_ascii() => Terminator::Any(val),
+                Ok(val) => {
+                    return Err(Error::InvalidConfig(format!(
+                        "terminator \"{val}\" is not an ASCII character. Use a decimal value below 128."
+                    )))
                 }
+                Err(_) if terminate_on == "crlf" => Terminator::CRLF,
+                Err(_) => Terminator::Any(parse_byte_option("terminator", terminate_on)?),
             }
         } else if is_tsv {
             Terminator::Any(b'\n')
@@ -161,44 +398,138 @@ impl<'a> Config<'a> {
             Trim::None
         };
 
-        Config {
+        Ok(Config {
             delimiter,
+            description_column: args.description_column.as_deref(),
+            context_column: args.context_column.as_deref(),
+            source_locale: args.source_locale.as_deref(),
+            detect_delimiter: args.delimiter.is_none() && !is_tsv,
+            detect_terminator: args.terminator.is_none(),
+            detect_escape: args.escape_char.is_none(),
+            encoding,
             escape_char,
+            quote: b'"',
             ignored_headings,
             flexible: !args.inflexible,
+            omit_empty: args.omit_empty,
+            on_duplicate,
+            format,
             output_dir,
             output_filename,
+            properties_utf8: args.properties_utf8,
+            sheet,
+            all_sheets: args.all_sheets,
+            spreadsheet,
             terminator_char,
             trim_whitespace,
+            type_column: args.type_column.as_deref(),
+            typed_columns: args
+                .typed_columns
+                .as_ref()
+                .map(|list| list.split(',').collect()),
+            typed_values: args.typed_values,
+        })
+    }
+
+    /// Returns `true` if any columns or keys are configured to be written as typed JSON values.
+    /// Keys ending in `[]` are parsed as JSON whether or not this is set.
+    pub fn typed(&self) -> bool {
+        self.typed_values || self.typed_columns.is_some() || self.type_column.is_some()
+    }
+
+    /// Uses the parts of a detected dialect which weren't configured. Any line ending other
+    /// than `\n` reads records ending in `\r`, `\n` or `\r\n`. Unless an escape character was
+    /// configured, quotes inside quoted cells are escaped by doubling the detected quote, so a
+    /// `"` inside a `'` quoted cell is kept as is.
+    ///
+    /// * `dialect` - dialect detected from the input file
+    pub fn apply_dialect(&mut self, dialect: &Dialect) {
+        if self.detect_delimiter {
+            self.delimiter = dialect.delimiter;
+            self.quote = dialect.quote;
+            if self.detect_escape {
+                self.escape_char = dialect.quote;
+            }
+        }
+        if self.detect_terminator && dialect.line_ending != LineEnding::Lf {
+            self.terminator_char = Terminator::CRLF;
+        }
+    }
+}
+
+/// Parses a single byte option such as the delimiter, given as a single ASCII character, an
+/// escape sequence (`\t`, `\n`, `\r`, `\\`, `\0` or `\xHH`) or a name such as `tab` or `backslash`.
+///
+/// * `option` - name of the option, used in error messages
+/// * `value` - value passed for the option
+fn parse_byte_option(option: &str, value: &str) -> Result<u8, Error> {
+    let invalid = |reason: &str| {
+        Error::InvalidConfig(format!(
+            "{option} \"{value}\" {reason}. Use a single ASCII character, an escape sequence like `\\t`, or one of `tab`, `comma`, `semicolon`, `colon`, `pipe` or `space`."
+        ))
+    };
+
+    match value {
+        "" => Err(invalid("is empty")),
+        "tab" | "\\t" => Ok(b'\t'),
+        "comma" => Ok(b','),
+        "semicolon" => Ok(b';'),
+        "colon" => Ok(b':'),
+        "pipe" => Ok(b'|'),
+        "space" => Ok(b' '),
+        "\\n" => Ok(b'\n'),
+        "\\r" => Ok(b'\r'),
+        "backslash" | "\\\\" => Ok(b'\\'),
+        "\\0" => Ok(0),
+        _ => {
+            if let Some(hex) = value.strip_prefix("\\x") {
+                return match u8::from_str_radix(hex, 16) {
+                    Ok(byte)
+                        if hex.len() == 2
+                            && hex.bytes().all(|digit| digit.is_ascii_hexdigit())
+                            && byte.is_ascii() =>
+                    {
+                        Ok(byte)
+                    }
+                    _ => Err(invalid(
+                        "is not an ASCII hex escape between `\\x00` and `\\x7f`",
+                    )),
+                };
+            }
+
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(char), None) if char.is_ascii() => Ok(char as u8),
+                (Some(_), None) => Err(invalid("is not an ASCII character")),
+                _ => Err(invalid("is more than one character")),
+            }
         }
     }
 }
 
 /// Checks if special command line shell characters like "~" or "$", which
-/// are used for expansions are present anywhere in a text string.
+/// are used for expansions are present anywhere in a path, reporting a
+/// [`Diagnostic::UnexpandedPath`] for each one found.
 ///
-/// * `char` - the special character to check for
-/// * `text` - the text to search for the special character
-fn special_character_check(char: &str, text: &str) {
-    if text.contains(char) {
-        println!(
-            "{} Path {} contained the literal '{}' character and will be treated as a filename.\n{}\n",
-            "Warning:".on_yellow().blink(),
-            text.underline(),
-            char.bold(),
-            "If this was not intended, try again without wrapping the path inside of quotes to allow expansion by your shell.".bold(),
-        );
+/// * `path` - path given on the command line
+/// * `diagnostics` - receives a diagnostic for each special character in the path
+pub fn check_path(path: &str, diagnostics: &mut dyn DiagnosticSink) {
+    for character in ['~', '$'] {
+        if path.contains(character) {
+            diagnostics.report(Diagnostic::UnexpandedPath {
+                path: path.to_owned(),
+                character,
+            });
+        }
     }
 }
 
 /// Takes a path argument and returns a representation of that file system
-/// location (`PathBuf`), or an `io::Error` if this representation can't be created.
+/// location (`PathBuf`), or an error if this representation can't be created.
 ///
 /// * `file` - string slice which is the path to a file system location.
-pub fn get_file_location(file: &str) -> Result<PathBuf, io::Error> {
+pub fn get_file_location(file: &str) -> Result<PathBuf, Error> {
     let cwd = std::env::current_dir()?;
-    special_character_check("~", file);
-    special_character_check("$", file);
 
     let full_path = PathBuf::from(file);
 
@@ -209,45 +540,80 @@ pub fn get_file_location(file: &str) -> Result<PathBuf, io::Error> {
     }
 }
 
-/// Returns a configured CSV reader for the specified file, or an error.
+/// Returns a configured CSV reader for the specified file, or an error. Files which aren't
+/// UTF-8 are transcoded first, using the configured encoding or the one detected.
 ///
 /// * `file_path` - relative or absolute path to file
 /// * `config` - parsed command line configuration
-pub fn get_file_reader(file_path: &str, config: &Config) -> Result<Reader<fs::File>, csv::Error> {
-    let csv_path = get_file_location(file_path).expect("Unable to create path");
+pub fn get_file_reader(file_path: &str, config: &Config) -> Result<Reader<InputFile>, Error> {
+    let csv_path = get_file_location(file_path)?;
+    let input = InputFile::open(&csv_path, config.encoding)?;
+
+    Ok(get_csv_reader(input, config))
+}
 
+/// Returns a CSV reader for an input which is already open, such as an [`InputFile`] whose
+/// dialect was detected with [`Dialect::sniff`].
+///
+/// * `input` - input to read CSV records from
+/// * `config` - parsed command line configuration
+pub fn get_csv_reader<R: io::Read>(input: R, config: &Config) -> Reader<R> {
     ReaderBuilder::new()
         .delimiter(config.delimiter)
         .escape(Some(config.escape_char))
         .flexible(config.flexible)
+        .quote(config.quote)
         .terminator(config.terminator_char)
         .trim(config.trim_whitespace)
-        .from_path(csv_path)
+        .from_reader(input)
 }
 
 /// Entry point for library to generate JSON translation files.
 ///
-/// * `reader` - a configured CSV reader
+/// Converts every record in a single pass with [`convert_fast`], reporting diagnostics as they
+/// happen, and returns the first error instead of converting the input again.
+///
+/// * `reader` - a configured CSV reader, whose headings have already been read
 /// * `headings` - heading row for the CSV file
-/// * `rows` - number of rows that are in the CSV file
 /// * `config` - parsed command line configuration
-pub fn run(
-    reader: &mut Reader<fs::File>,
+/// * `diagnostics` - receives conversion events and the location of each file written
+pub fn run<R: io::Read>(
+    reader: &mut Reader<R>,
     headings: &StringRecord,
-    rows: usize,
     config: &Config,
-) -> Result<(), io::Error> {
-    if generate_json_fast(reader, headings, rows, config).is_err() {
-        generate_json(reader, headings, rows, config)?
-    }
-    Ok(())
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    let translations = convert_fast(reader, headings, config, diagnostics)?;
+
+    generators::write_output(&translations, config, diagnostics)
 }
 
 #[cfg(test)]
 mod get_file_location_tests {
-    use crate::get_file_location;
+    use crate::{check_path, get_file_location, Diagnostic};
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn it_reports_unexpanded_shell_characters_in_paths() {
+        let mut diagnostics = Vec::new();
+        check_path("~/$HOME/file.csv", &mut diagnostics);
+        check_path("./file.csv", &mut diagnostics);
+
+        assert_eq!(
+            diagnostics,
+            [
+                Diagnostic::UnexpandedPath {
+                    path: "~/$HOME/file.csv".into(),
+                    character: '~'
+                },
+                Diagnostic::UnexpandedPath {
+                    path: "~/$HOME/file.csv".into(),
+                    character: '$'
+                }
+            ]
+        );
+    }
+
     #[test]
     #[cfg(not(target_os = "windows"))]
     fn it_turns_a_relative_location_into_a_full_path() {
@@ -284,25 +650,262 @@ mod get_file_location_tests {
 
 #[cfg(test)]
 mod get_file_reader_tests {
-    use csv::{Terminator, Trim};
+    use csv::Trim;
 
     use crate::{get_file_reader, Config};
 
-    const CONFIG: Config = Config {
-        delimiter: b',',
-        escape_char: b'"',
-        ignored_headings: None,
-        flexible: true,
-        output_dir: "",
-        output_filename: None,
-        terminator_char: Terminator::CRLF,
-        trim_whitespace: Trim::Fields,
-    };
+    fn test_config() -> Config<'static> {
+        Config {
+            trim_whitespace: Trim::Fields,
+            ..Config::default()
+        }
+    }
 
     #[test]
     fn it_has_file_reader_that_is_configured_to_have_csv_headers() {
-        let path = get_file_reader("package.json", &CONFIG).unwrap();
+        let path = get_file_reader("package.json", &test_config()).unwrap();
 
         assert!(path.has_headers());
     }
 }
+
+#[cfg(test)]
+mod config_tests {
+    use crate::{parse_byte_option, CliArgs, Config, Dialect, DuplicatePolicy, Error, LineEnding};
+    use csv::Terminator;
+    use std::ffi::OsStr;
+
+    fn cli_args() -> CliArgs {
+        CliArgs {
+            all_sheets: false,
+            context_column: None,
+            delimiter: None,
+            description_column: None,
+            encoding: None,
+            escape_char: None,
+            format: None,
+            ignored_headings: None,
+            inflexible: false,
+            omit_empty: false,
+            on_duplicate: None,
+            output_dir: None,
+            output_filename: None,
+            properties_utf8: false,
+            report: None,
+            report_file: None,
+            sheet: None,
+            source_locale: None,
+            terminator: None,
+            trim: None,
+            type_column: None,
+            typed_columns: None,
+            typed_values: false,
+            version: None,
+            file: String::from("translations.csv"),
+        }
+    }
+
+    #[test]
+    fn it_uses_tab_delimiter_for_tsv_files() {
+        let args = cli_args();
+        let config = Config::new(&args, Some(OsStr::new("tsv"))).unwrap();
+
+        assert_eq!(config.delimiter, b'\t');
+        assert_eq!(config.escape_char, b'\\');
+    }
+
+    #[test]
+    fn it_detects_spreadsheet_input_from_the_file_extension() {
+        let args = cli_args();
+
+        for extension in ["xlsx", "ODS", "xls"] {
+            let config = Config::new(&args, Some(OsStr::new(extension)));
+            if cfg!(feature = "spreadsheet") {
+                assert!(config.unwrap().spreadsheet);
+            } else {
+                assert!(matches!(config, Err(Error::InvalidConfig(_))));
+            }
+        }
+        assert!(
+            !Config::new(&args, Some(OsStr::new("csv")))
+                .unwrap()
+                .spreadsheet
+        );
+    }
+
+    #[test]
+    fn it_rejects_all_sheets_with_an_output_filename() {
+        let args = CliArgs {
+            all_sheets: true,
+            output_filename: Some(String::from("common")),
+            ..cli_args()
+        };
+
+        assert!(matches!(
+            Config::new(&args, None),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_all_sheets_with_the_webextension_format() {
+        let args = CliArgs {
+            all_sheets: true,
+            format: Some(String::from("webextension")),
+            ..cli_args()
+        };
+
+        assert!(matches!(
+            Config::new(&args, None),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn it_only_uses_a_detected_dialect_for_options_not_configured() {
+        let dialect = Dialect {
+            delimiter: b';',
+            quote: b'\'',
+            line_ending: LineEnding::CrLf,
+        };
+        let args = cli_args();
+        let mut config = Config::new(&args, None).unwrap();
+        config.apply_dialect(&dialect);
+
+        assert_eq!(config.delimiter, b';');
+        assert_eq!(config.quote, b'\'');
+        assert_eq!(config.escape_char, b'\'');
+
+        let args = CliArgs {
+            escape_char: Some(String::from("backslash")),
+            ..cli_args()
+        };
+        let mut escaped_config = Config::new(&args, None).unwrap();
+        escaped_config.apply_dialect(&dialect);
+
+        assert_eq!(escaped_config.quote, b'\'');
+        assert_eq!(escaped_config.escape_char, b'\\');
+
+        let args = CliArgs {
+            delimiter: Some(String::from(",")),
+            ..cli_args()
+        };
+        let mut tsv_config = Config::new(&args, Some(OsStr::new("tsv"))).unwrap();
+        tsv_config.apply_dialect(&dialect);
+
+        assert_eq!(tsv_config.delimiter, b',');
+        assert_eq!(tsv_config.quote, b'"');
+        assert!(matches!(tsv_config.terminator_char, Terminator::CRLF));
+    }
+
+    #[test]
+    fn it_parses_escaped_and_named_single_byte_options() {
+        let args = CliArgs {
+            delimiter: Some(String::from("\\t")),
+            escape_char: Some(String::from("backslash")),
+            terminator: Some(String::from("pipe")),
+            ..cli_args()
+        };
+        let config = Config::new(&args, None).unwrap();
+
+        assert_eq!(config.delimiter, b'\t');
+        assert_eq!(config.escape_char, b'\\');
+        assert!(matches!(config.terminator_char, Terminator::Any(b'|')));
+        assert_eq!(parse_byte_option("delimiter", "semicolon").unwrap(), b';');
+        assert_eq!(parse_byte_option("delimiter", "\\x1f").unwrap(), 0x1f);
+        assert_eq!(parse_byte_option("delimiter", "#").unwrap(), b'#');
+        for invalid in ["", "§", ";;", "\\x+1", "\\xff"] {
+            assert!(matches!(
+                parse_byte_option("delimiter", invalid),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn it_returns_an_error_for_an_invalid_terminator() {
+        for terminator in ["\\n\\r", "128", "255"] {
+            let args = CliArgs {
+                terminator: Some(String::from(terminator)),
+                ..cli_args()
+            };
+
+            assert!(matches!(
+                Config::new(&args, None),
+                Err(Error::InvalidConfig(_))
+            ));
+        }
+
+        let args = CliArgs {
+            terminator: Some(String::from("127")),
+            ..cli_args()
+        };
+        assert!(matches!(
+            Config::new(&args, None).unwrap().terminator_char,
+            Terminator::Any(127)
+        ));
+    }
+
+    #[test]
+    fn it_parses_the_duplicate_key_policy() {
+        let args = CliArgs {
+            on_duplicate: Some(String::from("keep-longest")),
+            ..cli_args()
+        };
+        let config = Config::new(&args, None).unwrap();
+
+        assert_eq!(config.on_duplicate, DuplicatePolicy::KeepLongest);
+        assert!(matches!(
+            "newest".parse::<DuplicatePolicy>(),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod run_tests {
+    use crate::{get_file_reader, run, Config, Diagnostic, Error, Location};
+    use std::fs;
+
+    const HEADINGS: &str = "id,da_DK,de_DE,en_US,es_ES,fr_FR,it_IT,nl_NL,pt_BR,pt_PT,sv_SE,notes\n";
+
+    #[test]
+    fn it_returns_the_first_conversion_error_without_converting_again() {
+        let dir = std::env::temp_dir().join("translocate_run_error_test");
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("run_error.csv");
+        let mut data = HEADINGS.as_bytes().to_vec();
+        data.extend_from_slice(b"first,,b,c,d,e,f,g,h,i,j,\n");
+        data.extend_from_slice(b"second,a,b,c,d,e,f,g,h,i,j,\xff\n");
+        fs::write(&input, data).unwrap();
+
+        let config = Config {
+            // Read the invalid UTF-8 as is, rather than transcoding it from Windows-1252.
+            encoding: Some(encoding_rs::UTF_8),
+            ignored_headings: Some(vec!["notes"]),
+            output_dir: dir.to_str().unwrap(),
+            ..Config::default()
+        };
+        let mut reader = get_file_reader(input.to_str().unwrap(), &config).unwrap();
+        let headings = reader.headers().unwrap().clone();
+        let mut diagnostics = Vec::new();
+
+        let result = run(&mut reader, &headings, &config, &mut diagnostics);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The empty value is reported as it is read, and nothing is converted or written.
+        assert!(matches!(result, Err(Error::Csv(_))));
+        assert_eq!(
+            diagnostics,
+            [Diagnostic::EmptyValue {
+                locale: "da_DK".into(),
+                key: "first".into(),
+                location: Location {
+                    record: 1,
+                    line: 2,
+                    byte: HEADINGS.len() as u64
+                }
+            }]
+        );
+    }
+}
@@ -10,62 +10,236 @@
 //!   "app.title": "Translocate means to move from one place to another."
 //! }
 //! ```
-use csv::Reader;
-use std::{io, process};
-use translocate::{get_file_location, get_file_reader, run, CliArgs, Config};
+use std::{fs, io, path::Path, process};
+use translocate::{
+    check_path, get_csv_reader, get_file_location, run, CliArgs, Config, Diagnostic,
+    DiagnosticSink, Dialect, Error, InputFile, Report,
+};
 use yansi::Paint;
 
 const APP_DESC: &str = "trans·lo·cate, verb, to move from one place to another.";
 const MISSING_FILE_ERR: &str = "Try again with the absolute (full) path to the file.";
+const DUPE_KEY_NOTICE: &str = "translation keys overwritten during conversion.\n";
+
+/// Renders conversion diagnostics to the console.
+struct ConsoleDiagnostics;
+
+impl DiagnosticSink for ConsoleDiagnostics {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        match diagnostic {
+            Diagnostic::DialectDetected(dialect) => println!(
+                "Detected `{}` delimiter, `{}` quotes and {:?} line endings.",
+                printable(dialect.delimiter),
+                printable(dialect.quote),
+                dialect.line_ending
+            ),
+            Diagnostic::UnexpandedPath { path, character } => println!(
+                "{} Path {} contained the literal '{}' character and will be treated as a filename.\n{}\n",
+                "Warning:".on_yellow().blink(),
+                path.underline(),
+                character.bold(),
+                "If this was not intended, try again without wrapping the path inside of quotes to allow expansion by your shell.".bold(),
+            ),
+            Diagnostic::DuplicateKey(overwritten) => match overwritten.previous_line {
+                Some(previous_line) => println!(
+                    "{} key \"{}\" from line {} overwritten by record {} (line {}, byte {}).",
+                    "Warning:".on_yellow().italic(),
+                    overwritten.key,
+                    previous_line,
+                    overwritten.location.record,
+                    overwritten.location.line,
+                    overwritten.location.byte
+                ),
+                None => println!(
+                    "{} key \"{}\" overwritten by record {} (line {}, byte {}).",
+                    "Warning:".on_yellow().italic(),
+                    overwritten.key,
+                    overwritten.location.record,
+                    overwritten.location.line,
+                    overwritten.location.byte
+                ),
+            },
+            Diagnostic::DuplicateKeys(keys) => {
+                for dupe in keys {
+                    let lines = dupe
+                        .lines
+                        .iter()
+                        .map(|line| line.to_string())
+                        .collect::<Vec<String>>();
+                    println!(
+                        "{} key \"{}\" appears on more than one line ({}).",
+                        "Warning:".on_yellow().italic(),
+                        dupe.key,
+                        lines.join(", ")
+                    );
+                }
+            }
+            Diagnostic::Converted { overwritten, .. } if overwritten > 0 => {
+                println!("\n{overwritten} {DUPE_KEY_NOTICE}")
+            }
+            Diagnostic::FileWritten(file) => {
+                println!(
+                    "{} written to {}.",
+                    file.path.file_name().unwrap_or_default().to_string_lossy(),
+                    file.path.parent().unwrap_or(&file.path).to_string_lossy()
+                );
+                if file.omitted > 0 {
+                    println!("  {} keys with empty values omitted.", file.omitted)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Shows a delimiter or quote character, writing a tab as `\t`.
+fn printable(char: u8) -> String {
+    match char {
+        b'\t' => String::from("\\t"),
+        _ => char::from(char).to_string(),
+    }
+}
+
+/// Prints an error message for the user and exits the process with a failure status. Only a
+/// missing input file is reported as not found with a hint, other errors name their own path.
+fn exit_with_error(file_path: &str, error: Error) -> ! {
+    let input_path = get_file_location(file_path).ok();
+
+    match error {
+        Error::File { path, error }
+            if error.kind() == io::ErrorKind::NotFound && Some(&path) == input_path.as_ref() =>
+        {
+            eprintln!(
+                "{} file `{}` not found. {}",
+                "Error:".bold().on_bright_red(),
+                file_path.bold(),
+                MISSING_FILE_ERR.underline()
+            )
+        }
+        error => eprintln!("{} {}", "Error:".bold().on_bright_red(), error),
+    }
+    process::exit(1)
+}
 
 /// Main entry point for translocate binary
-fn main() -> Result<(), std::io::Error> {
+fn main() {
     let cli: CliArgs = argh::from_env();
 
     if cli.version.is_some() {
-        return Ok(println!(
+        println!(
             "{} v{}\n{}",
             env!("CARGO_PKG_NAME").underline(),
             env!("CARGO_PKG_VERSION"),
             APP_DESC.italic()
-        ));
+        );
+        return;
     }
 
     let file_path = &cli.file;
-    let csv_path = get_file_location(file_path)?;
-    let config = Config::new(&cli, csv_path.extension());
-
-    let mut reader = match get_file_reader(file_path, &config) {
-        Ok(res) => res,
-        Err(err) => match err.kind() {
-            csv::ErrorKind::Io(io_error) => {
-                if io_error.kind() == io::ErrorKind::NotFound {
-                    eprintln!(
-                        "{} file `{}` not found. {}",
-                        "Error:".bold().on_bright_red(),
-                        file_path.bold(),
-                        MISSING_FILE_ERR.underline()
-                    );
-                    process::exit(1)
-                } else {
-                    eprintln!("{} {}", "Error:".bold().on_bright_red(), io_error);
-                    process::exit(1)
-                }
-            }
-            _ => {
-                eprintln!("{} {}", "Error:".bold().on_bright_red(), err);
-                process::exit(1)
-            }
-        },
+    if let Err(error) = convert_file(&cli) {
+        exit_with_error(file_path, error)
+    }
+}
+
+/// Converts the sheets of a spreadsheet input file.
+#[cfg(feature = "spreadsheet")]
+fn convert_spreadsheet(
+    path: &Path,
+    config: &Config,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    translocate::run_spreadsheet(path, config, diagnostics)
+}
+
+/// Spreadsheet input is already rejected by [`Config::new`] when the `spreadsheet` feature is disabled.
+#[cfg(not(feature = "spreadsheet"))]
+fn convert_spreadsheet(
+    _path: &Path,
+    _config: &Config,
+    _diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    Err(Error::InvalidConfig(String::from(
+        "spreadsheet input requires the `spreadsheet` feature.",
+    )))
+}
+
+/// Reads the input file named in the command line arguments and writes the converted output.
+fn convert_file(cli: &CliArgs) -> Result<(), Error> {
+    let report_to_stdout = match cli.report.as_deref() {
+        Some("json") => true,
+        Some(format) => {
+            return Err(Error::InvalidConfig(format!(
+                "unsupported report format \"{format}\". The only supported format is `json`."
+            )))
+        }
+        None => false,
+    };
+
+    if !report_to_stdout {
+        let paths = [
+            Some(&cli.file),
+            cli.output_dir.as_ref(),
+            cli.report_file.as_ref(),
+        ];
+        for path in paths.into_iter().flatten() {
+            check_path(path, &mut ConsoleDiagnostics);
+        }
+    }
+
+    let csv_path = get_file_location(&cli.file)?;
+    let mut config = Config::new(cli, csv_path.extension())?;
+
+    let mut input = None;
+    let mut dialect = None;
+    if !config.spreadsheet {
+        let mut file = InputFile::open(&csv_path, config.encoding)?;
+        if config.detect_delimiter || config.detect_terminator {
+            dialect = Dialect::sniff(&mut file)?;
+        }
+        if let Some(dialect) = &dialect {
+            config.apply_dialect(dialect);
+        }
+        input = Some(file);
+    }
+
+    // Diagnostics are only collected when a report was requested, as a large input can have
+    // many duplicate keys and empty values.
+    let mut report = (report_to_stdout || cli.report_file.is_some())
+        .then(|| Report::new(csv_path.clone(), config.delimiter));
+    let mut diagnostics = |diagnostic: Diagnostic| match &mut report {
+        Some(report) if report_to_stdout => report.report(diagnostic),
+        Some(report) => {
+            ConsoleDiagnostics.report(diagnostic.clone());
+            report.report(diagnostic);
+        }
+        None => ConsoleDiagnostics.report(diagnostic),
     };
 
-    let headings = reader.headers()?.clone();
-    let rows = Reader::from_path(&csv_path)?.byte_records().count();
+    if let Some(dialect) = dialect {
+        diagnostics(Diagnostic::DialectDetected(dialect));
+    }
+
+    match input {
+        Some(input) => {
+            let mut reader = get_csv_reader(input, &config);
+            let headings = reader.headers()?.clone();
+            run(&mut reader, &headings, &config, &mut diagnostics)?;
+        }
+        None => convert_spreadsheet(&csv_path, &config, &mut diagnostics)?,
+    }
+
+    if let (Some(report), Some(report_file)) = (&report, &cli.report_file) {
+        let report_path = get_file_location(report_file)?;
+        fs::write(&report_path, report.to_json()?).map_err(|error| Error::File {
+            path: report_path.clone(),
+            error,
+        })?;
+    }
 
-    if let Err(error) = run(&mut reader, &headings, rows, &config) {
-        eprintln!("{}", error);
-        process::exit(1)
+    match &report {
+        Some(report) if report_to_stdout => println!("{}", report.to_json()?),
+        _ => println!("\n✨🎉✨ {}", "Conversion successful!".bold()),
     }
 
-    Ok(println!("\n✨🎉✨ {}", "Conversion successful!".bold()))
+    Ok(())
 }
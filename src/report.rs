@@ -0,0 +1,194 @@
+use crate::diagnostics::{Diagnostic, DiagnosticSink};
+use crate::dialect::LineEnding;
+use crate::translations::DuplicateKey;
+use crate::Error;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// A locale output file listed in a [`Report`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReportFile {
+    /// Locale the file contains, or `None` if it combines several locales.
+    pub locale: Option<String>,
+    /// Location of the written file.
+    pub path: PathBuf,
+    /// Number of translation keys written to the file.
+    pub keys: usize,
+    /// Number of translation keys left out of the file because they have no value in its locale.
+    pub omitted_keys: usize,
+}
+
+/// A translation key overwritten by a later record, listed in a [`Report`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReportDuplicate {
+    /// The translation key which was overwritten.
+    pub key: String,
+    /// Line of the record which overwrote the key.
+    pub line: u64,
+    /// Byte offset of the record which overwrote the key.
+    pub byte: u64,
+    /// Line of the earlier record which defined the overwritten value.
+    pub previous_line: Option<u64>,
+}
+
+/// An empty translation value listed in a [`Report`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReportEmptyValue {
+    /// Locale (column heading) the value was missing from.
+    pub locale: String,
+    /// The translation key with the empty value.
+    pub key: String,
+    /// Line of the record with the empty value.
+    pub line: u64,
+}
+
+/// The dialect detected from an input file, listed in a [`Report`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ReportDialect {
+    /// Detected column delimiter.
+    pub delimiter: String,
+    /// Detected quote character.
+    pub quote: String,
+    /// Detected line ending.
+    pub line_ending: LineEnding,
+}
+
+/// Machine readable summary of a conversion, built by collecting [`Diagnostic`] events.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Input file which was converted.
+    pub input_file: PathBuf,
+    /// Column delimiter used when parsing the input file.
+    pub delimiter: String,
+    /// Dialect detected from the input file, if it wasn't fully configured.
+    pub detected_dialect: Option<ReportDialect>,
+    /// Number of records read, excluding the heading row. Includes every sheet converted from a spreadsheet.
+    pub rows: usize,
+    /// Every file written, in the order it was written.
+    pub locales: Vec<ReportFile>,
+    /// Translation keys overwritten by later records.
+    pub duplicate_keys: Vec<ReportDuplicate>,
+    /// Translation keys which appear in more than one record, with every line each appears on.
+    pub repeated_keys: Vec<DuplicateKey>,
+    /// Translation keys with an empty value for a locale.
+    pub empty_values: Vec<ReportEmptyValue>,
+}
+
+impl Report {
+    /// Creates an empty report for the given input file and delimiter.
+    ///
+    /// * `input_file` - path of the file being converted
+    /// * `delimiter` - column delimiter used when parsing the input file
+    pub fn new(input_file: PathBuf, delimiter: u8) -> Report {
+        Report {
+            input_file,
+            delimiter: char::from(delimiter).to_string(),
+            ..Report::default()
+        }
+    }
+
+    /// Serializes the report as pretty printed JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl DiagnosticSink for Report {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        match diagnostic {
+            Diagnostic::DialectDetected(dialect) => {
+                self.detected_dialect = Some(ReportDialect {
+                    delimiter: char::from(dialect.delimiter).to_string(),
+                    quote: char::from(dialect.quote).to_string(),
+                    line_ending: dialect.line_ending,
+                })
+            }
+            Diagnostic::DuplicateKey(overwritten) => self.duplicate_keys.push(ReportDuplicate {
+                key: overwritten.key,
+                line: overwritten.location.line,
+                byte: overwritten.location.byte,
+                previous_line: overwritten.previous_line,
+            }),
+            Diagnostic::DuplicateKeys(keys) => self.repeated_keys.extend(keys),
+            Diagnostic::EmptyValue {
+                locale,
+                key,
+                location,
+            } => self.empty_values.push(ReportEmptyValue {
+                locale,
+                key,
+                line: location.line,
+            }),
+            Diagnostic::Converted { records, .. } => self.rows += records,
+            Diagnostic::FileWritten(file) => self.locales.push(ReportFile {
+                locale: file.locale,
+                path: file.path,
+                keys: file.keys,
+                omitted_keys: file.omitted,
+            }),
+            // Only relevant to a person running the command in a shell.
+            Diagnostic::UnexpandedPath { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::Report;
+    use crate::{
+        Diagnostic, DiagnosticSink, Dialect, DuplicateKey, LineEnding, Location, OverwrittenKey,
+        WrittenFile,
+    };
+    use pretty_assertions::assert_eq;
+    use std::path::PathBuf;
+
+    #[test]
+    fn it_collects_diagnostics_into_a_json_report() {
+        let mut report = Report::new(PathBuf::from("translations.csv"), b';');
+
+        report.report(Diagnostic::DialectDetected(Dialect {
+            delimiter: b';',
+            quote: b'"',
+            line_ending: LineEnding::CrLf,
+        }));
+        report.report(Diagnostic::DuplicateKey(OverwrittenKey {
+            key: "app.title".into(),
+            location: Location {
+                record: 2,
+                line: 3,
+                byte: 30,
+            },
+            previous_line: Some(2),
+        }));
+        report.report(Diagnostic::DuplicateKeys(vec![DuplicateKey {
+            key: "app.title".into(),
+            lines: vec![2, 3],
+        }]));
+        report.report(Diagnostic::Converted {
+            records: 2,
+            overwritten: 1,
+        });
+        report.report(Diagnostic::FileWritten(WrittenFile {
+            path: PathBuf::from("en_US.json"),
+            locale: Some("en_US".into()),
+            keys: 1,
+            omitted: 0,
+        }));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "input_file": "translations.csv",
+                "delimiter": ";",
+                "detected_dialect": { "delimiter": ";", "quote": "\"", "line_ending": "CRLF" },
+                "rows": 2,
+                "locales": [{ "locale": "en_US", "path": "en_US.json", "keys": 1, "omitted_keys": 0 }],
+                "duplicate_keys": [{ "key": "app.title", "line": 3, "byte": 30, "previous_line": 2 }],
+                "repeated_keys": [{ "key": "app.title", "lines": [2, 3] }],
+                "empty_values": []
+            })
+        );
+    }
+}
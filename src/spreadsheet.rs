@@ -0,0 +1,358 @@
+//! Reads translations from Excel (`.xlsx`, `.xlsm`, `.xlsb`, `.xls`) and OpenDocument (`.ods`)
+//! spreadsheets, so they can be converted without exporting to CSV first.
+
+use crate::generators::{self, RecordSource};
+use crate::{Config, DiagnosticSink, Error};
+use calamine::{open_workbook_auto, Data, ExcelDateTime, Reader};
+use csv::{Position, StringRecord, Trim};
+use std::path::Path;
+
+/// A single sheet of a spreadsheet, read into memory.
+#[derive(Clone, Debug)]
+pub struct Sheet {
+    /// Name of the sheet, as shown on its tab.
+    pub name: String,
+    /// The first non-empty row of the sheet.
+    pub headings: StringRecord,
+    records: Vec<StringRecord>,
+}
+
+impl Sheet {
+    /// Returns a [`RecordSource`] over every record after the heading row.
+    pub fn records(&self) -> SheetRecords<'_> {
+        SheetRecords {
+            records: &self.records,
+            next: 0,
+        }
+    }
+}
+
+/// Reads the records of a [`Sheet`] in order.
+pub struct SheetRecords<'a> {
+    records: &'a [StringRecord],
+    next: usize,
+}
+
+impl RecordSource for SheetRecords<'_> {
+    fn read_record(&mut self, record: &mut StringRecord) -> Result<bool, Error> {
+        match self.records.get(self.next) {
+            Some(next) => {
+                record.clone_from(next);
+                self.next += 1;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Reads the sheets selected by `config` from a spreadsheet file. Unless `all_sheets` is set,
+/// this is the sheet named by `sheet`, or the first sheet if no sheet was chosen.
+///
+/// * `file_path` - path to the spreadsheet file
+/// * `config` - parsed command line configuration
+pub fn read_sheets(file_path: &Path, config: &Config) -> Result<Vec<Sheet>, Error> {
+    let mut workbook = open_workbook_auto(file_path).map_err(spreadsheet_error)?;
+    let sheet_names = workbook.sheet_names();
+
+    let selected = if config.all_sheets {
+        sheet_names
+    } else {
+        vec![select_sheet(&sheet_names, config.sheet)?]
+    };
+
+    selected
+        .into_iter()
+        .map(|name| {
+            let range = workbook.worksheet_range(&name).map_err(spreadsheet_error)?;
+            let first_line = range.start().map_or(0, |(row, _)| u64::from(row)) + 1;
+            let mut rows = range
+                .rows()
+                .enumerate()
+                .filter(|(_, cells)| cells.iter().any(|cell| !matches!(cell, Data::Empty)))
+                .map(|(idx, cells)| {
+                    let mut record = cells.iter().map(cell_text).collect::<StringRecord>();
+                    if matches!(config.trim_whitespace, Trim::Fields | Trim::All) {
+                        record.trim();
+                    }
+                    (first_line + idx as u64, record)
+                });
+
+            let headings = rows.next().map(|(_, record)| record).unwrap_or_default();
+            let records = rows
+                .enumerate()
+                .map(|(idx, (line, mut record))| {
+                    let mut position = Position::new();
+                    position.set_line(line).set_record(idx as u64 + 1);
+                    record.set_position(Some(position));
+                    record
+                })
+                .collect();
+
+            Ok(Sheet {
+                name,
+                headings,
+                records,
+            })
+        })
+        .collect()
+}
+
+/// Returns the text of a cell as a spreadsheet shows it with its default format. Numbers are
+/// written without a trailing `.0` and rounded to 15 significant digits, dates and times as
+/// `2024-12-18 09:30:00`, with only the part which is set, and durations as `36:00:00`.
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Float(value) => number_text(*value),
+        Data::DateTime(datetime) if datetime.is_datetime() => datetime_text(datetime),
+        Data::DateTime(duration) => {
+            let seconds = (duration.as_f64() * 86_400.0).round() as i64;
+            format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds % 3600 / 60,
+                seconds % 60
+            )
+        }
+        _ => cell.to_string(),
+    }
+}
+
+/// Writes a number with at most 15 significant digits, the precision spreadsheets show.
+fn number_text(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+
+    let integer_digits = value.abs().log10().floor() as i32 + 1;
+    let decimals = (15 - integer_digits).clamp(0, 20) as usize;
+    let text = format!("{value:.decimals$}");
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_owned()
+    } else {
+        text
+    }
+}
+
+/// Writes a date as `2024-12-18`, a time as `09:30:00`, or both.
+fn datetime_text(datetime: &ExcelDateTime) -> String {
+    let (year, month, day, hour, minute, second, _) = datetime.to_ymd_hms_milli();
+    let date = format!("{year:04}-{month:02}-{day:02}");
+    let time = format!("{hour:02}:{minute:02}:{second:02}");
+    let serial = datetime.as_f64();
+
+    if serial.fract() == 0.0 {
+        date
+    } else if serial < 1.0 {
+        time
+    } else {
+        format!("{date} {time}")
+    }
+}
+
+/// Finds the sheet chosen with `--sheet`, by name or else by its 1-based position.
+fn select_sheet(sheet_names: &[String], sheet: Option<&str>) -> Result<String, Error> {
+    let sheet = match sheet {
+        Some(sheet) => sheet,
+        None => {
+            return sheet_names
+                .first()
+                .cloned()
+                .ok_or_else(|| Error::Spreadsheet(String::from("the file contains no sheets")))
+        }
+    };
+
+    if let Some(name) = sheet_names.iter().find(|name| *name == sheet) {
+        return Ok(name.clone());
+    }
+
+    sheet
+        .parse::<usize>()
+        .ok()
+        .and_then(|position| position.checked_sub(1))
+        .and_then(|idx| sheet_names.get(idx))
+        .cloned()
+        .ok_or_else(|| {
+            Error::InvalidConfig(format!(
+                "sheet \"{sheet}\" not found. Available sheets are: {}.",
+                sheet_names.join(", ")
+            ))
+        })
+}
+
+fn spreadsheet_error(error: calamine::Error) -> Error {
+    Error::Spreadsheet(error.to_string())
+}
+
+/// Entry point for library to generate JSON translation files from a spreadsheet.
+///
+/// Each selected sheet is converted like [`run`](crate::run) converts a CSV file. When
+/// `all_sheets` is set, every sheet is treated as a namespace, and written to a file named
+/// after the sheet inside a directory for each locale.
+///
+/// * `file_path` - path to the spreadsheet file
+/// * `config` - parsed command line configuration
+/// * `diagnostics` - receives conversion events and the location of each file written
+pub fn run_spreadsheet(
+    file_path: &Path,
+    config: &Config,
+    diagnostics: &mut dyn DiagnosticSink,
+) -> Result<(), Error> {
+    for sheet in read_sheets(file_path, config)? {
+        let mut records = sheet.records();
+        let translations =
+            generators::convert_fast(&mut records, &sheet.headings, config, diagnostics)?;
+
+        if config.all_sheets {
+            let sheet_config = Config {
+                output_filename: Some(&sheet.name),
+                ..config.clone()
+            };
+            generators::write_output(&translations, &sheet_config, diagnostics)?;
+        } else {
+            generators::write_output(&translations, config, diagnostics)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod spreadsheet_tests {
+    use super::{cell_text, read_sheets, run_spreadsheet, select_sheet};
+    use crate::{Config, Diagnostic, Error, Location};
+    use calamine::{Data, ExcelDateTime, ExcelDateTimeType};
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+
+    fn test_config() -> Config<'static> {
+        Config {
+            spreadsheet: true,
+            ..Config::default()
+        }
+    }
+
+    fn sheet_names() -> Vec<String> {
+        vec![String::from("common"), String::from("errors")]
+    }
+
+    #[test]
+    fn it_selects_a_sheet_by_name_or_position() {
+        assert_eq!(select_sheet(&sheet_names(), None).unwrap(), "common");
+        assert_eq!(
+            select_sheet(&sheet_names(), Some("errors")).unwrap(),
+            "errors"
+        );
+        assert_eq!(select_sheet(&sheet_names(), Some("2")).unwrap(), "errors");
+        assert!(matches!(
+            select_sheet(&sheet_names(), Some("0")),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            select_sheet(&sheet_names(), Some("warnings")),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn it_writes_cells_as_the_spreadsheet_shows_them() {
+        let datetime = |serial| {
+            Data::DateTime(ExcelDateTime::new(
+                serial,
+                ExcelDateTimeType::DateTime,
+                false,
+            ))
+        };
+        let cells = [
+            Data::Float(1.0),
+            Data::Float(-2.5),
+            Data::Float(0.1 + 0.2),
+            Data::Float(1e16),
+            Data::Int(3),
+            datetime(45644.0),
+            datetime(45644.395833333336),
+            datetime(0.75),
+            Data::DateTime(ExcelDateTime::new(1.5, ExcelDateTimeType::TimeDelta, false)),
+            Data::String(String::from("1.0")),
+        ];
+
+        assert_eq!(
+            cells.iter().map(cell_text).collect::<Vec<_>>(),
+            [
+                "1",
+                "-2.5",
+                "0.3",
+                "10000000000000000",
+                "3",
+                "2024-12-18",
+                "2024-12-18 09:30:00",
+                "18:00:00",
+                "36:00:00",
+                "1.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reads_records_with_their_row_numbers() {
+        let path = Path::new("tests/fixtures/translations.xlsx");
+        let config = Config {
+            sheet: Some("2"),
+            ..test_config()
+        };
+        let sheets = read_sheets(path, &config).unwrap();
+
+        assert_eq!(sheets.len(), 1);
+        assert_eq!(sheets[0].name, "errors");
+        assert_eq!(
+            sheets[0].headings.iter().collect::<Vec<_>>(),
+            ["id", "en_US", "fr_FR"]
+        );
+        assert_eq!(sheets[0].records.len(), 2);
+        assert_eq!(
+            Location::from(sheets[0].records[1].position()),
+            Location {
+                record: 2,
+                line: 4,
+                byte: 0
+            }
+        );
+    }
+
+    #[test]
+    fn it_writes_every_sheet_as_a_namespace() {
+        let dir = std::env::temp_dir().join("translocate_all_sheets_test");
+        fs::create_dir_all(&dir).unwrap();
+        let config = Config {
+            all_sheets: true,
+            output_dir: dir.to_str().unwrap(),
+            ..test_config()
+        };
+        let mut diagnostics = Vec::new();
+
+        run_spreadsheet(
+            Path::new("tests/fixtures/translations.ods"),
+            &config,
+            &mut diagnostics,
+        )
+        .unwrap();
+        let common = fs::read_to_string(dir.join("fr_FR").join("common.json")).unwrap();
+        let errors = fs::read_to_string(dir.join("en_US").join("errors.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            common,
+            "{\n  \"app.count\": \"3\",\n  \"app.title\": \"Bonjour\"\n}\n"
+        );
+        assert_eq!(
+            errors,
+            "{\n  \"error.missing\": \"Not found\",\n  \"error.server\": \"Server error\"\n}\n"
+        );
+        let written = diagnostics
+            .iter()
+            .filter(|diagnostic| matches!(diagnostic, Diagnostic::FileWritten(_)))
+            .count();
+        assert_eq!(written, 4);
+    }
+}
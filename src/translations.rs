@@ -1,91 +1,479 @@
-use serde::{Deserialize, Serialize};
+use crate::values::ValueType;
+use crate::{DuplicatePolicy, Error};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
 
-/// Represents the different types of data we expect to see in a CSV/TSV file.
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(untagged)]
-pub enum LangData {
-    Integer(i64),
-    Float(f64),
-    String(String),
-}
-
-/// List of languages we will attempt to (de)serialize from/to.
-/// To support more languages when (de)serializing they need to be
-/// added to this struct.
+/// Fields represent the data in CSV file headers that we want to get/convert.
+/// To support more languages when (de)serializing they need to be added to this struct.
+///
+/// Languages are fields of the record itself rather than a flattened struct, as flattening
+/// buffers cells and parses them as numbers where possible. Every value is kept as the exact
+/// text of its cell, so numbers such as `1.50` or `007` aren't reformatted.
 #[derive(Debug, Deserialize, Serialize)]
-struct Languages {
+pub struct TranslationRecord {
+    pub id: String,
     #[serde(alias = "da_DK")]
     #[serde(alias = "da-DK")]
-    da_dk: LangData,
+    da_dk: String,
     #[serde(alias = "de_DE")]
     #[serde(alias = "de-DE")]
-    de_de: LangData,
+    de_de: String,
     #[serde(alias = "en_US")]
     #[serde(alias = "en-US")]
-    en_us: LangData,
+    en_us: String,
     #[serde(alias = "es_ES")]
     #[serde(alias = "es-ES")]
-    es_es: LangData,
+    es_es: String,
     #[serde(alias = "fr_FR")]
     #[serde(alias = "fr-FR")]
-    fr_fr: LangData,
+    fr_fr: String,
     #[serde(alias = "it_IT")]
     #[serde(alias = "it-IT")]
-    it_it: LangData,
+    it_it: String,
     #[serde(alias = "nl_NL")]
     #[serde(alias = "nl-NL")]
-    nl_nl: LangData,
+    nl_nl: String,
     #[serde(alias = "pt_BR")]
     #[serde(alias = "pt-BR")]
-    pt_br: LangData,
+    pt_br: String,
     #[serde(alias = "pt_PT")]
     #[serde(alias = "pt-PT")]
-    pt_pt: LangData,
+    pt_pt: String,
     #[serde(alias = "sv_SE")]
     #[serde(alias = "sv-SE")]
-    sv_se: LangData,
-}
-
-/// Fields represent the data in CSV file headers that we want to get/convert.
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Translations {
-    id: String,
-    #[serde(flatten)]
-    languages: Languages,
+    sv_se: String,
 }
 
 pub trait FormatTranslation {
-    fn format_lang(&self, lang: &str) -> (&str, &LangData);
+    fn format_lang(&self, lang: &str) -> Result<(&str, &str), Error>;
 }
 
 /// Provides data from recognized languages as a tuple of two columns (id, language).
-impl FormatTranslation for Translations {
-    /// Provides serialized data for the matching lang argument.
-    fn format_lang(&self, lang: &str) -> (&str, &LangData) {
-        match lang {
-            "da_DK" => (&self.id, &self.languages.da_dk),
-            "da-DK" => (&self.id, &self.languages.da_dk),
-            "de_DE" => (&self.id, &self.languages.de_de),
-            "de-DE" => (&self.id, &self.languages.de_de),
-            "en_US" => (&self.id, &self.languages.en_us),
-            "en-US" => (&self.id, &self.languages.en_us),
-            "es_ES" => (&self.id, &self.languages.es_es),
-            "es-ES" => (&self.id, &self.languages.es_es),
-            "fr_FR" => (&self.id, &self.languages.fr_fr),
-            "fr-FR" => (&self.id, &self.languages.fr_fr),
-            "it_IT" => (&self.id, &self.languages.it_it),
-            "it-IT" => (&self.id, &self.languages.it_it),
-            "nl_NL" => (&self.id, &self.languages.nl_nl),
-            "nl-NL" => (&self.id, &self.languages.nl_nl),
-            "pt_BR" => (&self.id, &self.languages.pt_br),
-            "pt-BR" => (&self.id, &self.languages.pt_br),
-            "pt_PT" => (&self.id, &self.languages.pt_pt),
-            "pt-PT" => (&self.id, &self.languages.pt_pt),
-            "sv_SE" => (&self.id, &self.languages.sv_se),
-            "sv-SE" => (&self.id, &self.languages.sv_se),
+impl FormatTranslation for TranslationRecord {
+    /// Provides serialized data for the matching lang argument, or an error for an unsupported lang.
+    fn format_lang(&self, lang: &str) -> Result<(&str, &str), Error> {
+        let data = match lang {
+            "da_DK" => &self.da_dk,
+            "da-DK" => &self.da_dk,
+            "de_DE" => &self.de_de,
+            "de-DE" => &self.de_de,
+            "en_US" => &self.en_us,
+            "en-US" => &self.en_us,
+            "es_ES" => &self.es_es,
+            "es-ES" => &self.es_es,
+            "fr_FR" => &self.fr_fr,
+            "fr-FR" => &self.fr_fr,
+            "it_IT" => &self.it_it,
+            "it-IT" => &self.it_it,
+            "nl_NL" => &self.nl_nl,
+            "nl-NL" => &self.nl_nl,
+            "pt_BR" => &self.pt_br,
+            "pt-BR" => &self.pt_br,
+            "pt_PT" => &self.pt_pt,
+            "pt-PT" => &self.pt_pt,
+            "sv_SE" => &self.sv_se,
+            "sv-SE" => &self.sv_se,
             // Don't call this for non-lang fields, e.g. 'id'.
-            // panic is intentional here so filter non-langs first.
-            &_ => unimplemented!(),
+            &_ => return Err(Error::UnknownLocale(lang.to_owned())),
+        };
+
+        Ok((&self.id, data))
+    }
+}
+
+/// Where a record starts in the input file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// Number of the record, starting from 1 for the first record after the heading row.
+    pub record: u64,
+    /// Line the record starts on, starting from 1 for the heading row.
+    pub line: u64,
+    /// Byte offset the record starts at.
+    pub byte: u64,
+}
+
+impl From<Option<&csv::Position>> for Location {
+    fn from(position: Option<&csv::Position>) -> Self {
+        match position {
+            Some(pos) => Location {
+                record: pos.record(),
+                line: pos.line(),
+                byte: pos.byte(),
+            },
+            None => Location::default(),
         }
     }
 }
+
+/// A translation key which was given a new value by a later record in the input file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverwrittenKey {
+    /// The translation key which was overwritten.
+    pub key: String,
+    /// Location of the record which overwrote the key.
+    pub location: Location,
+    /// Line of the earlier record which defined the overwritten value.
+    pub previous_line: Option<u64>,
+}
+
+/// A translation key which appears in more than one record of the input file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DuplicateKey {
+    /// The duplicated translation key.
+    pub key: String,
+    /// Every line the key appears on.
+    pub lines: Vec<u64>,
+}
+
+/// Location of a value within a [`LocaleColumn`]'s text buffer, and the line it was read from.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    start: usize,
+    end: usize,
+    line: u64,
+}
+
+/// Values for a single locale, stored by key index in one shared text buffer so each
+/// cell doesn't need its own allocation. Replaced values are left in the buffer.
+#[derive(Debug, Default)]
+struct LocaleColumn {
+    text: String,
+    spans: Vec<Option<Span>>,
+}
+
+impl LocaleColumn {
+    fn get(&self, key_idx: usize) -> Option<&str> {
+        self.spans
+            .get(key_idx)
+            .copied()
+            .flatten()
+            .map(|span| &self.text[span.start..span.end])
+    }
+
+    /// Returns the line the value at `key_idx` was read from.
+    fn line(&self, key_idx: usize) -> Option<u64> {
+        self.spans
+            .get(key_idx)
+            .copied()
+            .flatten()
+            .map(|span| span.line)
+    }
+
+    fn set(&mut self, key_idx: usize, value: &str, line: u64) {
+        if key_idx >= self.spans.len() {
+            self.spans.resize(key_idx + 1, None);
+        }
+
+        let start = self.text.len();
+        self.text.push_str(value);
+        self.spans[key_idx] = Some(Span {
+            start,
+            end: self.text.len(),
+            line,
+        });
+    }
+}
+
+/// Translations read from an input file, grouped by locale.
+///
+/// Every translation key is stored once in a shared key table, and each locale stores its
+/// values in a column indexed by key, so memory use grows with the size of the input
+/// rather than with the number of keys multiplied by the number of locales.
+///
+/// Produced by [`convert`](crate::convert) or [`convert_fast`](crate::convert_fast) without
+/// writing anything to disk. Use a [`TranslationWriter`](crate::TranslationWriter) to output them.
+#[derive(Debug, Default)]
+pub struct Translations {
+    /// Maps each translation key to its index in the locale columns, ordered by key.
+    keys: BTreeMap<Box<str>, usize>,
+    /// Line each translation key was most recently read from, by key index.
+    lines: Vec<u64>,
+    /// Values for each locale (column heading), ordered by locale.
+    locales: BTreeMap<String, LocaleColumn>,
+    /// The locale whose column was read first, which is the first locale heading of the input.
+    first_locale: Option<String>,
+    /// Value type declared for each translation key, by key index.
+    value_types: Vec<Option<ValueType>>,
+    /// Description of each translation key from the description column, by key index.
+    descriptions: LocaleColumn,
+    /// Context of each translation key from the context column, by key index.
+    contexts: LocaleColumn,
+    /// Translation keys overwritten during conversion, in the order they were encountered.
+    pub overwritten: Vec<OverwrittenKey>,
+}
+
+impl Translations {
+    /// Returns the number of distinct translation keys.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns `true` if no translation keys were found.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Iterates over every translation key, ordered by key.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(|key| key.as_ref())
+    }
+
+    /// Iterates over the index of every translation key, in the same order as [`Translations::keys`].
+    pub(crate) fn key_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.keys.values().copied()
+    }
+
+    /// Iterates over every locale, ordered by locale.
+    pub fn locales(&self) -> impl Iterator<Item = LocaleTranslations<'_>> {
+        self.locales
+            .iter()
+            .map(move |(name, column)| LocaleTranslations {
+                name,
+                column,
+                keys: &self.keys,
+            })
+    }
+
+    /// Returns the translations for a single locale, if it exists.
+    pub fn locale(&self, name: &str) -> Option<LocaleTranslations<'_>> {
+        self.locales
+            .get_key_value(name)
+            .map(|(name, column)| LocaleTranslations {
+                name,
+                column,
+                keys: &self.keys,
+            })
+    }
+
+    /// Returns the translations for the first locale heading of the input file, if any.
+    pub fn first_locale(&self) -> Option<LocaleTranslations<'_>> {
+        self.locale(self.first_locale.as_deref()?)
+    }
+
+    /// Returns the value of `key` in `locale`, if it exists.
+    pub fn get(&self, locale: &str, key: &str) -> Option<&str> {
+        self.locale(locale)?.get(key)
+    }
+
+    /// Returns the value type declared for `key` by a type column or a `[]` key suffix, if any.
+    pub fn value_type(&self, key: &str) -> Option<ValueType> {
+        let key_idx = *self.keys.get(key)?;
+        self.value_types.get(key_idx).copied().flatten()
+    }
+
+    /// Returns `true` if any key has a declared value type.
+    pub(crate) fn has_value_types(&self) -> bool {
+        self.value_types.iter().any(Option::is_some)
+    }
+
+    /// Declares the value type of the key at `key_idx`.
+    pub(crate) fn set_value_type(&mut self, key_idx: usize, value_type: ValueType) {
+        if key_idx >= self.value_types.len() {
+            self.value_types.resize(key_idx + 1, None);
+        }
+        self.value_types[key_idx] = Some(value_type);
+    }
+
+    /// Returns the description of `key` from the description column, if it has one.
+    pub fn description(&self, key: &str) -> Option<&str> {
+        self.descriptions.get(*self.keys.get(key)?)
+    }
+
+    /// Sets the description of the key at `key_idx`, replacing any earlier description.
+    pub(crate) fn set_description(&mut self, key_idx: usize, description: &str) {
+        self.descriptions
+            .set(key_idx, description, self.lines[key_idx]);
+    }
+
+    /// Returns the context of `key` from the context column, if it has one.
+    pub fn context(&self, key: &str) -> Option<&str> {
+        self.contexts.get(*self.keys.get(key)?)
+    }
+
+    /// Sets the context of the key at `key_idx`, replacing any earlier context.
+    pub(crate) fn set_context(&mut self, key_idx: usize, context: &str) {
+        self.contexts.set(key_idx, context, self.lines[key_idx]);
+    }
+
+    /// Adds `key` to the key table if it is new, and records that it is being read from `line`.
+    /// Values inserted for the key are recorded as read from that line.
+    ///
+    /// Returns the key's index.
+    pub(crate) fn add_key(&mut self, key: &str, line: u64) -> usize {
+        if let Some(&key_idx) = self.keys.get(key) {
+            self.lines[key_idx] = line;
+            return key_idx;
+        }
+
+        let key_idx = self.keys.len();
+        self.keys.insert(key.into(), key_idx);
+        self.lines.push(line);
+        key_idx
+    }
+
+    /// Adds `locale` without giving any key a value, so it is written even if every value is omitted.
+    pub(crate) fn add_locale(&mut self, locale: &str) {
+        if !self.locales.contains_key(locale) {
+            self.first_locale.get_or_insert_with(|| locale.to_owned());
+            self.locales
+                .insert(locale.to_owned(), LocaleColumn::default());
+        }
+    }
+
+    /// Sets `value` for the key at `key_idx` in `locale`. When the key already has a value,
+    /// `policy` decides whether the existing value is replaced.
+    ///
+    /// Returns the line the replaced value was read from, if an existing value was replaced.
+    pub(crate) fn insert(
+        &mut self,
+        locale: &str,
+        key_idx: usize,
+        value: &str,
+        policy: DuplicatePolicy,
+    ) -> Option<u64> {
+        let line = self.lines[key_idx];
+        let column = match self.locales.get_mut(locale) {
+            Some(column) => column,
+            None => {
+                self.first_locale.get_or_insert_with(|| locale.to_owned());
+                self.locales.entry(locale.to_owned()).or_default()
+            }
+        };
+
+        if let Some(old) = column.get(key_idx) {
+            let replace = match policy {
+                // An empty value never replaces an existing non-empty value.
+                DuplicatePolicy::LastWins | DuplicatePolicy::Error => {
+                    !value.is_empty() || old.is_empty()
+                }
+                DuplicatePolicy::FirstWins => old.is_empty(),
+                DuplicatePolicy::KeepLongest => value.chars().count() > old.chars().count(),
+            };
+
+            if !replace {
+                return None;
+            }
+            let previous_line = column.line(key_idx);
+            column.set(key_idx, value, line);
+            return previous_line;
+        }
+
+        column.set(key_idx, value, line);
+        None
+    }
+}
+
+/// Translation keys and values of a single locale, borrowed from [`Translations`].
+///
+/// Serializes as a map of `{ "localization-key": "localized text" }`, ordered by key.
+#[derive(Clone, Copy, Debug)]
+pub struct LocaleTranslations<'a> {
+    name: &'a str,
+    column: &'a LocaleColumn,
+    keys: &'a BTreeMap<Box<str>, usize>,
+}
+
+impl<'a> LocaleTranslations<'a> {
+    /// Returns the locale (column heading) name.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the value of `key`, if this locale has one.
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        let column = self.column;
+        self.keys.get(key).and_then(|&key_idx| column.get(key_idx))
+    }
+
+    /// Iterates over every translation key and value in this locale, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        let column = self.column;
+        self.keys
+            .iter()
+            .filter_map(move |(key, &key_idx)| Some((key.as_ref(), column.get(key_idx)?)))
+    }
+
+    /// Returns the number of translation keys without a value in this locale, such as empty
+    /// values omitted with `omit_empty`.
+    pub fn omitted(&self) -> usize {
+        self.keys.len() - self.len()
+    }
+
+    /// Returns the number of translation keys with a value in this locale.
+    pub fn len(&self) -> usize {
+        self.column
+            .spans
+            .iter()
+            .filter(|span| span.is_some())
+            .count()
+    }
+
+    /// Returns `true` if this locale has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Serialize for LocaleTranslations<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod translations_tests {
+    use super::Translations;
+    use crate::DuplicatePolicy;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn it_shares_keys_between_locale_columns() {
+        let mut translations = Translations::default();
+        let title = translations.add_key("app.title", 2);
+        let body = translations.add_key("app.body", 3);
+        translations.insert("en_US", title, "Title", DuplicatePolicy::LastWins);
+        translations.insert("en_US", body, "Body", DuplicatePolicy::LastWins);
+        translations.insert("da_DK", title, "Titel", DuplicatePolicy::LastWins);
+
+        assert_eq!(translations.add_key("app.title", 4), title);
+        assert_eq!(
+            translations.insert("da_DK", title, "Overskrift", DuplicatePolicy::LastWins),
+            Some(2)
+        );
+
+        assert_eq!(translations.len(), 2);
+        assert_eq!(
+            translations.keys().collect::<Vec<&str>>(),
+            ["app.body", "app.title"]
+        );
+        assert_eq!(translations.get("da_DK", "app.title"), Some("Overskrift"));
+        assert_eq!(translations.get("da_DK", "app.body"), None);
+        assert_eq!(translations.locale("da_DK").unwrap().len(), 1);
+        assert_eq!(translations.first_locale().unwrap().name(), "en_US");
+        assert_eq!(
+            serde_json::to_string(&translations.locale("en_US").unwrap()).unwrap(),
+            r#"{"app.body":"Body","app.title":"Title"}"#
+        );
+    }
+
+    #[test]
+    fn it_returns_the_line_of_the_value_which_was_replaced() {
+        let mut translations = Translations::default();
+        let title = translations.add_key("app.title", 2);
+        translations.insert("en_US", title, "Title", DuplicatePolicy::LastWins);
+        translations.add_key("app.title", 3);
+
+        assert_eq!(
+            translations.insert("en_US", title, "", DuplicatePolicy::LastWins),
+            None
+        );
+
+        translations.add_key("app.title", 4);
+
+        assert_eq!(
+            translations.insert("en_US", title, "Heading", DuplicatePolicy::LastWins),
+            Some(2)
+        );
+        assert_eq!(translations.get("en_US", "app.title"), Some("Heading"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Typed JSON values, for translation keys which hold numbers, booleans, lists or other
+//! configuration rather than text.
+
+use crate::Error;
+use serde_json::value::RawValue;
+use std::{fmt, str::FromStr};
+
+/// How the text of a cell is written as a JSON value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    /// Always a string. This is the default for every cell.
+    String,
+    /// A JSON number such as `3` or `1.50`, written with its exact text.
+    Number,
+    /// `true` or `false`, in any case.
+    Boolean,
+    /// `null`. Empty cells are also written as `null`.
+    Null,
+    /// Any JSON text, such as an inline array `["Jan", "Feb"]` or object.
+    Json,
+    /// A number, `true`, `false`, `null`, array or object if the cell contains one, or else a string.
+    Auto,
+}
+
+impl ValueType {
+    /// Returns the JSON value of `text` for this type, or `None` if `text` isn't a valid value
+    /// of the type and should be written as a string.
+    ///
+    /// * `text` - the exact text of a cell
+    pub fn to_json(self, text: &str) -> Option<Box<RawValue>> {
+        let raw = |json: &str| RawValue::from_string(json.to_owned()).ok();
+
+        match self {
+            ValueType::String => None,
+            ValueType::Number => json_number(text),
+            ValueType::Boolean if text.eq_ignore_ascii_case("true") => raw("true"),
+            ValueType::Boolean if text.eq_ignore_ascii_case("false") => raw("false"),
+            ValueType::Boolean => None,
+            ValueType::Null if text.is_empty() || text == "null" => raw("null"),
+            ValueType::Null => None,
+            ValueType::Json => raw(text.trim()),
+            ValueType::Auto => match text {
+                "true" | "false" | "null" => raw(text),
+                _ if text.starts_with('[') || text.starts_with('{') => raw(text),
+                _ => json_number(text),
+            },
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = Error;
+
+    fn from_str(value_type: &str) -> Result<Self, Self::Err> {
+        match value_type.trim().to_ascii_lowercase().as_str() {
+            "string" | "text" => Ok(ValueType::String),
+            "number" => Ok(ValueType::Number),
+            "boolean" | "bool" => Ok(ValueType::Boolean),
+            "null" => Ok(ValueType::Null),
+            "json" | "array" | "object" => Ok(ValueType::Json),
+            "auto" => Ok(ValueType::Auto),
+            _ => Err(Error::InvalidConfig(format!("unknown value type \"{value_type}\". Use one of `string`, `number`, `boolean`, `null`, `json`, `array`, `object` or `auto`."))),
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ValueType::String => "string",
+            ValueType::Number => "number",
+            ValueType::Boolean => "boolean",
+            ValueType::Null => "null",
+            ValueType::Json => "JSON value",
+            ValueType::Auto => "value",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Returns the text as a raw JSON number, so it's written exactly as it appears in the input,
+/// or `None` if it isn't a valid JSON number. Numbers with leading zeros like `007` aren't valid.
+fn json_number(text: &str) -> Option<Box<RawValue>> {
+    let starts_like_number = text.starts_with(|char: char| char == '-' || char.is_ascii_digit());
+
+    if starts_like_number && text.trim_end() == text {
+        RawValue::from_string(text.to_owned()).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod values_tests {
+    use super::ValueType;
+    use crate::Error;
+    use pretty_assertions::assert_eq;
+
+    fn to_json(value_type: ValueType, text: &str) -> Option<String> {
+        value_type.to_json(text).map(|raw| raw.get().to_owned())
+    }
+
+    #[test]
+    fn it_keeps_the_exact_text_of_numbers() {
+        let numbers = [
+            "1.50",
+            "-3",
+            "12345678901234567890",
+            "2e10",
+            "007",
+            "1,5",
+            "12 ",
+            "-",
+        ]
+        .map(|text| to_json(ValueType::Number, text));
+
+        assert_eq!(
+            numbers,
+            [
+                Some("1.50".into()),
+                Some("-3".into()),
+                Some("12345678901234567890".into()),
+                Some("2e10".into()),
+                None,
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_booleans_nulls_and_inline_json() {
+        assert_eq!(to_json(ValueType::Boolean, "TRUE"), Some("true".into()));
+        assert_eq!(to_json(ValueType::Boolean, "yes"), None);
+        assert_eq!(to_json(ValueType::Null, ""), Some("null".into()));
+        assert_eq!(
+            to_json(ValueType::Json, " [\"Jan\", \"Feb\"] "),
+            Some("[\"Jan\", \"Feb\"]".into())
+        );
+        assert_eq!(to_json(ValueType::Json, "[\"Jan\""), None);
+        assert_eq!(to_json(ValueType::String, "3"), None);
+    }
+
+    #[test]
+    fn it_detects_the_type_of_auto_values() {
+        let values = [
+            "3",
+            "false",
+            "null",
+            "{\"a\": 1}",
+            "[1, 2]",
+            "True",
+            "Hello",
+            "",
+        ]
+        .map(|text| to_json(ValueType::Auto, text));
+
+        assert_eq!(
+            values,
+            [
+                Some("3".into()),
+                Some("false".into()),
+                Some("null".into()),
+                Some("{\"a\": 1}".into()),
+                Some("[1, 2]".into()),
+                None,
+                None,
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_type_names() {
+        assert_eq!("Array".parse::<ValueType>().unwrap(), ValueType::Json);
+        assert_eq!("bool".parse::<ValueType>().unwrap(), ValueType::Boolean);
+        assert!(matches!(
+            "date".parse::<ValueType>(),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
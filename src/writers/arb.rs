@@ -0,0 +1,340 @@
+use super::{locale_tag, source_locale, write_locales, TranslationWriter, WrittenFile};
+use crate::translations::LocaleTranslations;
+use crate::{get_file_location, Config, Error, Translations};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::to_string_pretty;
+use std::fs::File;
+use std::io::Write;
+
+/// Prefix of the output files when `output_filename` isn't configured.
+const DEFAULT_PREFIX: &str = "app";
+
+/// Writes one Flutter Application Resource Bundle per locale, named `app_<locale>.arb` (or
+/// `<output_filename>_<locale>.arb`) with `en-US` style locales written as `en_US`.
+///
+/// Each file starts with its `@@locale`, and keys are followed by an `@key` metadata object with
+/// the key's description from the description column, and the placeholders used by its value in
+/// the source locale (the configured source locale, or else the first locale), e.g. `{name}` in
+/// `Hello {name}`. Keys with no value in the source locale use the placeholders of their own
+/// value. Keys with neither a description nor placeholders have no `@key` object.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ArbWriter;
+
+/// A locale's translations, serialized as ARB with an `@key` entry after every key which has
+/// metadata.
+struct ArbLocale<'a> {
+    tag: String,
+    locale: LocaleTranslations<'a>,
+    source: Option<LocaleTranslations<'a>>,
+    translations: &'a Translations,
+}
+
+impl Serialize for ArbLocale<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("@@locale", &self.tag)?;
+
+        for (key, value) in self.locale.iter() {
+            let source = self
+                .source
+                .and_then(|source| source.get(key))
+                .filter(|source| !source.is_empty())
+                .unwrap_or(value);
+            map.serialize_entry(key, value)?;
+
+            let metadata = ArbMetadata {
+                description: self.translations.description(key),
+                placeholders: Placeholders(placeholders(source)),
+            };
+            if !metadata.is_empty() {
+                map.serialize_entry(&format!("@{key}"), &metadata)?;
+            }
+        }
+
+        map.end()
+    }
+}
+
+/// The `@key` metadata of a translation key.
+#[derive(Serialize)]
+struct ArbMetadata<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Placeholders::is_empty")]
+    placeholders: Placeholders<'a>,
+}
+
+impl ArbMetadata<'_> {
+    fn is_empty(&self) -> bool {
+        self.description.is_none() && self.placeholders.is_empty()
+    }
+}
+
+/// Names of the placeholders in a value, serialized as an object with an empty object for each.
+struct Placeholders<'a>(Vec<&'a str>);
+
+impl Placeholders<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Serialize for Placeholders<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|name| (name, serde_json::Map::new())))
+    }
+}
+
+impl TranslationWriter for ArbWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let prefix = config.output_filename.unwrap_or(DEFAULT_PREFIX);
+        let source = source_locale(translations, config)?;
+
+        write_locales(translations, |locale| {
+            let tag = locale_tag(locale.name(), '_');
+            let mut filename = get_file_location(config.output_dir)?;
+            filename.push(format!("{prefix}_{tag}.arb"));
+
+            let arb = to_string_pretty(&ArbLocale {
+                tag,
+                locale,
+                source,
+                translations,
+            })?;
+            writeln!(
+                File::create(&filename).map_err(Error::file(&filename))?,
+                "{arb}"
+            )?;
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
+
+/// Finds the names of the `{name}` placeholders in an ICU message, in order of first use.
+/// Plural and select arguments such as `{count, plural, other{...}}` are placeholders too, and
+/// placeholders inside their branches are found, but the branch bodies themselves aren't.
+fn placeholders(message: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut pos = push_pattern_placeholders(message, 0, &mut names);
+    // Outside of a branch, an unmatched `}` is text.
+    while pos < message.len() {
+        pos = push_pattern_placeholders(message, pos + 1, &mut names);
+    }
+    names
+}
+
+/// Finds the placeholders of the message text starting at `pos`, up to the `}` which closes
+/// a plural or select branch. Returns the position of that `}`, or the end of the message.
+fn push_pattern_placeholders<'m>(
+    message: &'m str,
+    mut pos: usize,
+    names: &mut Vec<&'m str>,
+) -> usize {
+    while let Some(&byte) = message.as_bytes().get(pos) {
+        match byte {
+            b'{' => {
+                pos = push_argument_placeholders(message, pos + 1, names).unwrap_or(pos + 1);
+            }
+            b'}' => return pos,
+            _ => pos += 1,
+        }
+    }
+    message.len()
+}
+
+/// Reads the argument starting after a `{` at `start`, adding its name and the placeholders in
+/// any plural or select branches to `names`. Returns the position after the argument's closing
+/// `}`, or `None` if the `{` doesn't start an argument.
+fn push_argument_placeholders<'m>(
+    message: &'m str,
+    start: usize,
+    names: &mut Vec<&'m str>,
+) -> Option<usize> {
+    let bytes = message.as_bytes();
+    let skip_whitespace = |mut pos: usize| {
+        while bytes.get(pos).map_or(false, u8::is_ascii_whitespace) {
+            pos += 1;
+        }
+        pos
+    };
+    let word_end = |mut pos: usize| {
+        while bytes
+            .get(pos)
+            .map_or(false, |byte| byte.is_ascii_alphanumeric() || *byte == b'_')
+        {
+            pos += 1;
+        }
+        pos
+    };
+
+    let name_start = skip_whitespace(start);
+    let name_end = word_end(name_start);
+    let name = &message[name_start..name_end];
+    if !name.starts_with(|char: char| char.is_ascii_alphabetic() || char == '_') {
+        return None;
+    }
+
+    let mut pos = skip_whitespace(name_end);
+    let separator = match bytes.get(pos) {
+        Some(&byte @ (b'}' | b',')) => byte,
+        _ => return None,
+    };
+    if !names.contains(&name) {
+        names.push(name);
+    }
+    if separator == b'}' {
+        return Some(pos + 1);
+    }
+
+    let kind_start = skip_whitespace(pos + 1);
+    let kind_end = word_end(kind_start);
+    pos = skip_whitespace(kind_end);
+    let has_branches = matches!(
+        &message[kind_start..kind_end],
+        "plural" | "selectordinal" | "select"
+    ) && bytes.get(pos) == Some(&b',');
+    if !has_branches {
+        // Formatted arguments such as `{date, date, short}` have no placeholders in their style.
+        return Some(
+            message[pos..]
+                .find('}')
+                .map_or(message.len(), |end| pos + end + 1),
+        );
+    }
+
+    pos += 1;
+    loop {
+        pos = skip_whitespace(pos);
+        match bytes.get(pos) {
+            None => return Some(message.len()),
+            Some(b'}') => return Some(pos + 1),
+            // A branch body, after its selector such as `one`, `other` or `=0`.
+            Some(b'{') => {
+                pos = push_pattern_placeholders(message, pos + 1, names);
+                if pos < message.len() {
+                    pos += 1;
+                }
+            }
+            Some(_) => {
+                while bytes.get(pos).map_or(false, |byte| {
+                    !byte.is_ascii_whitespace() && !matches!(byte, b'{' | b'}')
+                }) {
+                    pos += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod arb_tests {
+    use super::{placeholders, ArbLocale};
+    use crate::{DuplicatePolicy, Translations};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn it_leaves_out_metadata_of_keys_without_a_description_or_placeholders() {
+        let mut translations = Translations::default();
+        for (key, value) in [
+            ("greeting", "Hello {name}"),
+            ("save", "Save"),
+            ("title", "Title"),
+        ] {
+            let key_idx = translations.add_key(key, 2);
+            translations.insert("en_US", key_idx, value, DuplicatePolicy::LastWins);
+            if key == "save" {
+                translations.set_description(key_idx, "Save button");
+            }
+        }
+
+        assert_eq!(
+            serde_json::to_value(ArbLocale {
+                tag: String::from("en_US"),
+                locale: translations.locale("en_US").unwrap(),
+                source: None,
+                translations: &translations,
+            })
+            .unwrap(),
+            serde_json::json!({
+                "@@locale": "en_US",
+                "greeting": "Hello {name}",
+                "@greeting": { "placeholders": { "name": {} } },
+                "save": "Save",
+                "@save": { "description": "Save button" },
+                "title": "Title"
+            })
+        );
+    }
+
+    #[test]
+    fn it_reads_placeholders_from_the_source_locale_unless_its_value_is_empty() {
+        let mut translations = Translations::default();
+        for (key, source, value) in [
+            ("greeting", "Hi {name}", "Hallo"),
+            ("bye", "", "Tschüss {name}"),
+        ] {
+            let key_idx = translations.add_key(key, 2);
+            translations.insert("en_US", key_idx, source, DuplicatePolicy::LastWins);
+            translations.insert("de_DE", key_idx, value, DuplicatePolicy::LastWins);
+        }
+
+        let arb = serde_json::to_value(ArbLocale {
+            tag: String::from("de_DE"),
+            locale: translations.locale("de_DE").unwrap(),
+            source: translations.first_locale(),
+            translations: &translations,
+        })
+        .unwrap();
+
+        assert_eq!(
+            arb["@greeting"],
+            serde_json::json!({ "placeholders": { "name": {} } })
+        );
+        assert_eq!(
+            arb["@bye"],
+            serde_json::json!({ "placeholders": { "name": {} } })
+        );
+    }
+
+    #[test]
+    fn it_finds_placeholders_in_order_of_first_use() {
+        assert_eq!(
+            placeholders("Hello {name}, you have {count} new {count}"),
+            ["name", "count"]
+        );
+        assert_eq!(
+            placeholders("{count, plural, =0{No items} other{{count} items in {cart}}}"),
+            ["count", "cart"]
+        );
+        assert_eq!(placeholders("{ 1 } {not closed { spaced }"), ["spaced"]);
+    }
+
+    #[test]
+    fn it_skips_the_bodies_of_plural_and_select_branches() {
+        assert_eq!(
+            placeholders("{count, plural, one{Item} other{Items}}"),
+            ["count"]
+        );
+        assert_eq!(
+            placeholders("{gender, select, male{He} female{She} other{They}} left {place}"),
+            ["gender", "place"]
+        );
+        assert_eq!(
+            placeholders(
+                "{n, plural, offset:1 =0{Nobody} other{{n} by {user}}} on {day, date, short}"
+            ),
+            ["n", "user", "day"]
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use super::{locale_file_path, locale_tag, write_locales, TranslationWriter, WrittenFile};
+use crate::translations::LocaleTranslations;
+use crate::{Config, Error, Translations};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+
+/// Writes one Project Fluent `.ftl` file per locale, named `<locale>.ftl` with `en_US` style
+/// locales written as `en-US`, or `<locale>/<output_filename>.ftl` when `output_filename` is
+/// configured.
+///
+/// Dotted keys are converted into Fluent identifiers, so `app.title` is written as `app-title`.
+/// When the key before the last `.` is a message itself, the key is written as an attribute of
+/// that message instead, so `login` and `login.placeholder` are written as `login` with a
+/// `.placeholder` attribute. Keys' descriptions are written as comments. Values are written as text,
+/// so any `{` and `}` in them are written as string literals rather than starting placeables.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FluentWriter;
+
+/// A Fluent message, and the translation keys written as its value and attributes.
+#[derive(Debug, Default)]
+struct Message<'a> {
+    key: Option<&'a str>,
+    attributes: BTreeMap<String, &'a str>,
+}
+
+impl TranslationWriter for FluentWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let messages = messages(translations)?;
+
+        write_locales(translations, |locale| {
+            let filename = locale_file_path(&locale_tag(locale.name(), '-'), "ftl", config)?;
+            fs::write(&filename, write_messages(&messages, locale, translations))
+                .map_err(Error::file(&filename))?;
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
+
+/// Groups every translation key into the Fluent message it is written as, ordered by identifier.
+/// Returns an error if two keys would be written with the same identifier.
+fn messages(translations: &Translations) -> Result<BTreeMap<String, Message<'_>>, Error> {
+    let keys = translations.keys().collect::<BTreeSet<&str>>();
+    let mut messages = BTreeMap::<String, Message>::new();
+
+    for key in translations.keys() {
+        let (message_key, attribute) = match attribute_of(key, &keys) {
+            Some((parent, attribute)) => (parent, Some(attribute)),
+            None => (key, None),
+        };
+        let id = identifier(key, message_key)?;
+        let message = messages.entry(id.clone()).or_default();
+
+        let existing = match attribute {
+            Some(attribute) => message.attributes.insert(identifier(key, attribute)?, key),
+            None => message.key.replace(key),
+        };
+        if let Some(existing) = existing {
+            return Err(Error::InvalidKey {
+                key: key.to_owned(),
+                reason: format!(
+                    "it is written as the same Fluent message as \"{existing}\" (`{id}`)."
+                ),
+            });
+        }
+    }
+
+    Ok(messages)
+}
+
+/// Returns the parent key and attribute name if `key` is written as an attribute, because the
+/// key before its last `.` is a message which isn't an attribute itself.
+fn attribute_of<'k>(key: &'k str, keys: &BTreeSet<&str>) -> Option<(&'k str, &'k str)> {
+    let (parent, attribute) = key.rsplit_once('.')?;
+    (keys.contains(parent) && attribute_of(parent, keys).is_none()).then_some((parent, attribute))
+}
+
+/// Converts part of a translation key into a Fluent identifier, replacing `.` and any other
+/// characters which aren't allowed with `-`.
+fn identifier(key: &str, part: &str) -> Result<String, Error> {
+    if !part.starts_with(|char: char| char.is_ascii_alphabetic()) {
+        return Err(Error::InvalidKey {
+            key: key.to_owned(),
+            reason: String::from("Fluent identifiers must start with a letter from a to z."),
+        });
+    }
+
+    Ok(part
+        .chars()
+        .map(|char| match char {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => char,
+            _ => '-',
+        })
+        .collect())
+}
+
+/// Writes the messages with a value in `locale` as Fluent source.
+fn write_messages(
+    messages: &BTreeMap<String, Message>,
+    locale: LocaleTranslations,
+    translations: &Translations,
+) -> String {
+    let mut ftl = String::new();
+
+    for (id, message) in messages {
+        let value = message.key.and_then(|key| locale.get(key));
+        let attributes = message
+            .attributes
+            .iter()
+            .filter_map(|(name, key)| Some((name, locale.get(key)?)))
+            .collect::<Vec<_>>();
+        if value.is_none() && attributes.is_empty() {
+            continue;
+        }
+
+        if let Some(description) = message.key.and_then(|key| translations.description(key)) {
+            for line in description.lines() {
+                ftl.push_str("# ");
+                ftl.push_str(line);
+                ftl.push('\n');
+            }
+        }
+
+        ftl.push_str(id);
+        match value {
+            Some(value) => push_pattern(&mut ftl, value, ""),
+            None => ftl.push_str(" =\n"),
+        }
+        for (name, value) in attributes {
+            ftl.push_str("    .");
+            ftl.push_str(name);
+            push_pattern(&mut ftl, value, "    ");
+        }
+    }
+
+    ftl
+}
+
+/// Appends ` = ` and a value, written on the following lines with an extra level of
+/// indentation if it has more than one line. Empty values are written as `{""}`.
+///
+/// * `indent` - indentation of the message or attribute the value belongs to
+fn push_pattern(output: &mut String, value: &str, indent: &str) {
+    if value.is_empty() {
+        output.push_str(" = {\"\"}\n");
+    } else if value.contains('\n') {
+        output.push_str(" =\n");
+        for line in value.lines() {
+            if !line.is_empty() {
+                output.push_str(indent);
+                output.push_str("    ");
+                push_line(output, line);
+            }
+            output.push('\n');
+        }
+    } else {
+        output.push_str(" = ");
+        push_line(output, value);
+        output.push('\n');
+    }
+}
+
+/// Appends a line of a value. Leading spaces, which Fluent would remove, a leading `[`, `*` or
+/// `.`, which would start a variant or attribute, and every `{` and `}`, which would start or end
+/// a placeable, are written as string literals.
+fn push_line(output: &mut String, line: &str) {
+    let text = line.trim_start_matches(' ');
+    let spaces = &line[..line.len() - text.len()];
+    if !spaces.is_empty() {
+        output.push_str("{\"");
+        output.push_str(spaces);
+        output.push_str("\"}");
+    }
+
+    for (idx, char) in text.char_indices() {
+        match char {
+            '[' | '*' | '.' if idx == 0 => push_literal(output, char),
+            '{' | '}' => push_literal(output, char),
+            _ => output.push(char),
+        }
+    }
+}
+
+/// Appends a character as a Fluent string literal placeable, e.g. `{"{"}`.
+fn push_literal(output: &mut String, char: char) {
+    output.push_str("{\"");
+    output.push(char);
+    output.push_str("\"}");
+}
+
+#[cfg(test)]
+mod fluent_tests {
+    use super::{messages, write_messages};
+    use crate::{DuplicatePolicy, Error, Translations};
+    use pretty_assertions::assert_eq;
+
+    fn translations(values: &[(&str, &str)]) -> Translations {
+        let mut translations = Translations::default();
+        for (line, (key, value)) in values.iter().enumerate() {
+            let key_idx = translations.add_key(key, line as u64 + 2);
+            translations.insert("en_US", key_idx, value, DuplicatePolicy::LastWins);
+        }
+        translations
+    }
+
+    #[test]
+    fn it_writes_dotted_keys_as_identifiers_and_attributes() {
+        let translations = translations(&[
+            ("app.title", "Translocate"),
+            ("login", "Log in"),
+            ("login.placeholder", "Email"),
+            ("login.placeholder.hint", "Work email"),
+            ("intro", "First line\n  indented\n\n* not a variant"),
+        ]);
+        let messages = messages(&translations).unwrap();
+
+        assert_eq!(
+            write_messages(
+                &messages,
+                translations.locale("en_US").unwrap(),
+                &translations
+            ),
+            "app-title = Translocate\n\
+             intro =\n    First line\n    {\"  \"}indented\n\n    {\"*\"} not a variant\n\
+             login = Log in\n    .placeholder = Email\n\
+             login-placeholder-hint = Work email\n"
+        );
+    }
+
+    #[test]
+    fn it_writes_braces_as_text_which_parses_back_to_the_value() {
+        use fluent_syntax::ast::{Entry, Expression, InlineExpression, PatternElement};
+
+        let values = ["Hello {name} and } bye", "{ $count } items", "{\n  }"];
+        let translations = translations(&[
+            ("greeting", values[0]),
+            ("items", values[1]),
+            ("multi", values[2]),
+        ]);
+        let ftl = write_messages(
+            &messages(&translations).unwrap(),
+            translations.locale("en_US").unwrap(),
+            &translations,
+        );
+        let resource = fluent_syntax::parser::parse(ftl.as_str()).unwrap();
+
+        let parsed = resource
+            .body
+            .iter()
+            .map(|entry| match entry {
+                Entry::Message(message) => message
+                    .value
+                    .as_ref()
+                    .unwrap()
+                    .elements
+                    .iter()
+                    .map(|element| match element {
+                        PatternElement::TextElement { value } => *value,
+                        PatternElement::Placeable {
+                            expression:
+                                Expression::Inline(InlineExpression::StringLiteral { value }),
+                        } => value,
+                        other => panic!("unexpected placeable {other:?}"),
+                    })
+                    .collect::<String>(),
+                other => panic!("unexpected entry {other:?}"),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(parsed, values);
+    }
+
+    #[test]
+    fn it_returns_an_error_for_keys_written_as_the_same_identifier() {
+        let translations = translations(&[("app.title", "a"), ("app-title", "b")]);
+
+        assert!(matches!(
+            messages(&translations),
+            Err(Error::InvalidKey { key, .. }) if key == "app.title"
+        ));
+        assert!(matches!(
+            messages(&self::translations(&[("404.title", "Not found")])),
+            Err(Error::InvalidKey { .. })
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use super::{locale_file_path, write_locales, TranslationWriter, WrittenFile};
+use crate::translations::LocaleTranslations;
+use crate::values::ValueType;
+use crate::{Config, Error, Translations};
+use serde::{Serialize, Serializer};
+use serde_json::to_string_pretty;
+use std::fs::File;
+use std::io::Write;
+
+/// Writes one pretty printed JSON file per locale, in the form
+/// `{ "localization-key": "localized text" }`.
+///
+/// Every value is written as a string, unless typed values are configured or declared by a key.
+/// Then values are written as the [`ValueType`] declared for their key, or detected for typed
+/// columns.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonWriter;
+
+/// A locale's translations, with values serialized as the JSON type configured for their key
+/// or column.
+struct TypedLocale<'a> {
+    locale: LocaleTranslations<'a>,
+    translations: &'a Translations,
+    column_type: Option<ValueType>,
+}
+
+impl Serialize for TypedLocale<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.locale.iter().map(|(key, value)| {
+            let value_type = self.translations.value_type(key).or(self.column_type);
+            (key, TypedValue(value, value_type))
+        }))
+    }
+}
+
+/// A cell value, serialized as its typed JSON value if it has one, or else a string.
+struct TypedValue<'a>(&'a str, Option<ValueType>);
+
+impl Serialize for TypedValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.1.and_then(|value_type| value_type.to_json(self.0)) {
+            Some(json) => json.serialize(serializer),
+            None => serializer.serialize_str(self.0),
+        }
+    }
+}
+
+impl TranslationWriter for JsonWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        write_locales(translations, |locale| {
+            let filename = locale_file_path(locale.name(), "json", config)?;
+            let json = if config.typed() || translations.has_value_types() {
+                let column_type = (config.typed_values
+                    || config
+                        .typed_columns
+                        .as_ref()
+                        .map_or(false, |columns| columns.contains(&locale.name())))
+                .then_some(ValueType::Auto);
+                to_string_pretty(&TypedLocale {
+                    locale,
+                    translations,
+                    column_type,
+                })?
+            } else {
+                to_string_pretty(&locale)?
+            };
+            writeln!(
+                File::create(&filename).map_err(Error::file(&filename))?,
+                "{json}"
+            )?;
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
@@ -0,0 +1,273 @@
+//! Output formats for converted translations.
+//!
+//! Every format implements [`TranslationWriter`], so conversion and writing are separate steps.
+//! Formats which output one file per locale can use [`write_locales`] to write every locale,
+//! and [`locale_file_path`] to honour the `output_dir` and `output_filename` configuration options.
+//! The format written by the binary is chosen with [`OutputFormat`].
+
+mod arb;
+mod fluent;
+mod json;
+mod properties;
+mod qt;
+mod resx;
+mod webextension;
+mod xcstrings;
+
+use crate::translations::LocaleTranslations;
+use crate::{get_file_location, Config, Error, Translations};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::fs::create_dir_all;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub use arb::ArbWriter;
+pub use fluent::FluentWriter;
+pub use json::JsonWriter;
+pub use properties::PropertiesWriter;
+pub use qt::QtWriter;
+pub use resx::ResxWriter;
+pub use webextension::WebExtensionWriter;
+pub use xcstrings::XcstringsWriter;
+
+/// Output format of the converted translations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One JSON file per locale, written by [`JsonWriter`].
+    #[default]
+    Json,
+    /// One Java `.properties` file per locale, written by [`PropertiesWriter`].
+    Properties,
+    /// One .NET `.resx` resource file per locale, written by [`ResxWriter`].
+    Resx,
+    /// One Flutter `.arb` file per locale, written by [`ArbWriter`].
+    Arb,
+    /// One Project Fluent `.ftl` file per locale, written by [`FluentWriter`].
+    Fluent,
+    /// Browser extension `_locales/<locale>/messages.json` files, written by [`WebExtensionWriter`].
+    WebExtension,
+    /// A single Xcode String Catalog containing every locale, written by [`XcstringsWriter`].
+    Xcstrings,
+    /// One Qt Linguist `.ts` file per locale, written by [`QtWriter`].
+    Qt,
+}
+
+impl OutputFormat {
+    /// Returns the writer which outputs this format.
+    pub fn writer(self) -> &'static dyn TranslationWriter {
+        match self {
+            OutputFormat::Json => &JsonWriter,
+            OutputFormat::Properties => &PropertiesWriter,
+            OutputFormat::Resx => &ResxWriter,
+            OutputFormat::Arb => &ArbWriter,
+            OutputFormat::Fluent => &FluentWriter,
+            OutputFormat::WebExtension => &WebExtensionWriter,
+            OutputFormat::Xcstrings => &XcstringsWriter,
+            OutputFormat::Qt => &QtWriter,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "json" => Ok(OutputFormat::Json),
+            "properties" => Ok(OutputFormat::Properties),
+            "resx" => Ok(OutputFormat::Resx),
+            "arb" => Ok(OutputFormat::Arb),
+            "fluent" | "ftl" => Ok(OutputFormat::Fluent),
+            "webextension" | "chrome" => Ok(OutputFormat::WebExtension),
+            "xcstrings" => Ok(OutputFormat::Xcstrings),
+            "qt" | "ts" => Ok(OutputFormat::Qt),
+            _ => Err(Error::InvalidConfig(format!(
+                "unknown output format \"{format}\". Use one of `json`, `properties`, `resx`, `arb`, `fluent`, `webextension`, `xcstrings` or `qt`."
+            ))),
+        }
+    }
+}
+
+/// An output file written by a [`TranslationWriter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrittenFile {
+    /// Location of the written file.
+    pub path: PathBuf,
+    /// Locale the file contains, or `None` if it combines several locales.
+    pub locale: Option<String>,
+    /// Number of translation keys written to the file.
+    pub keys: usize,
+    /// Number of translation keys left out of the file because they have no value in its locale.
+    pub omitted: usize,
+}
+
+/// Writes converted translations to one or more output files.
+pub trait TranslationWriter {
+    /// Writes `translations` using the output options in `config`, returning every file
+    /// which was written.
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error>;
+}
+
+/// Returns the path a locale file should be written to, creating the locale directory
+/// when `output_filename` is configured.
+///
+/// * `locale` - locale (column heading) being written
+/// * `extension` - file extension of the output format, without a leading `.`
+/// * `config` - parsed command line configuration
+pub fn locale_file_path(locale: &str, extension: &str, config: &Config) -> Result<PathBuf, Error> {
+    let mut filename = get_file_location(config.output_dir)?;
+
+    if let Some(outfile) = config.output_filename {
+        filename.push(locale);
+        create_dir_all(&filename).map_err(Error::file(&filename))?;
+        filename.push(format!("{outfile}.{extension}"));
+    } else {
+        filename.push(format!("{locale}.{extension}"));
+    }
+
+    Ok(filename)
+}
+
+/// Returns a locale (column heading) such as `en-US` or `en_US` with its language and region
+/// joined by `separator`, for formats which name their files or entries after the locale.
+///
+/// * `locale` - locale (column heading) being written
+/// * `separator` - separator expected by the output format, e.g. `_` for Java or `-` for .NET
+pub fn locale_tag(locale: &str, separator: char) -> String {
+    locale
+        .trim()
+        .chars()
+        .map(|char| match char {
+            '-' | '_' => separator,
+            _ => char,
+        })
+        .collect()
+}
+
+/// Returns the configured source locale, or else the first locale heading of the input file.
+/// Returns an error if the configured source locale isn't a column of the input file.
+fn source_locale<'t>(
+    translations: &'t Translations,
+    config: &Config,
+) -> Result<Option<LocaleTranslations<'t>>, Error> {
+    match config.source_locale {
+        Some(name) => translations.locale(name).map(Some).ok_or_else(|| {
+            Error::InvalidConfig(format!(
+                "source locale \"{name}\" isn't a column of the input file."
+            ))
+        }),
+        None => Ok(translations.first_locale()),
+    }
+}
+
+/// Appends `text` to `output`, escaping the characters which can't appear as is in XML text
+/// or attribute values.
+fn push_xml_escaped(output: &mut String, text: &str) {
+    for char in text.chars() {
+        match char {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            _ => output.push(char),
+        }
+    }
+}
+
+/// Calls `write_locale` for every locale in `translations`, returning the written files
+/// ordered by locale.
+///
+/// With the `parallel` feature enabled, locales are serialized and written concurrently.
+/// The returned files keep the same order either way, so output stays deterministic.
+///
+/// * `translations` - converted translations to write
+/// * `write_locale` - writes a single locale's translation keys and values
+pub fn write_locales<F>(
+    translations: &Translations,
+    write_locale: F,
+) -> Result<Vec<WrittenFile>, Error>
+where
+    F: Fn(LocaleTranslations<'_>) -> Result<WrittenFile, Error> + Sync,
+{
+    #[cfg(feature = "parallel")]
+    let locales = translations.locales().collect::<Vec<_>>().into_par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let locales = translations.locales();
+
+    locales.map(&write_locale).collect()
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use super::{locale_tag, push_xml_escaped, source_locale, JsonWriter, TranslationWriter};
+    use crate::{Config, DuplicatePolicy, Error, Translations};
+    use std::fs;
+
+    #[test]
+    fn it_returns_written_files_in_locale_order() {
+        let dir = std::env::temp_dir().join("translocate_writer_order_test");
+        let config = Config {
+            output_dir: dir.to_str().unwrap(),
+            ..Config::default()
+        };
+        let mut translations = Translations::default();
+        let key_idx = translations.add_key("app.title", 2);
+        for locale in ["sv_SE", "da_DK", "nl_NL", "de_DE"] {
+            translations.insert(locale, key_idx, locale, DuplicatePolicy::LastWins);
+        }
+        fs::create_dir_all(&dir).unwrap();
+
+        let written = JsonWriter.write(&translations, &config).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let locales = written
+            .iter()
+            .map(|file| file.locale.as_deref().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(locales, ["da_DK", "de_DE", "nl_NL", "sv_SE"]);
+    }
+
+    #[test]
+    fn it_joins_locale_tags_with_the_separator_of_the_format() {
+        assert_eq!(locale_tag("en-US", '_'), "en_US");
+        assert_eq!(locale_tag("pt_BR", '-'), "pt-BR");
+        assert_eq!(locale_tag("de", '-'), "de");
+    }
+
+    #[test]
+    fn it_uses_the_first_locale_column_unless_a_source_locale_is_configured() {
+        let mut translations = Translations::default();
+        let key_idx = translations.add_key("app.title", 2);
+        for locale in ["sv_SE", "da_DK"] {
+            translations.insert(locale, key_idx, locale, DuplicatePolicy::LastWins);
+        }
+        let source = |locale| {
+            let config = Config {
+                source_locale: locale,
+                ..Config::default()
+            };
+            source_locale(&translations, &config).map(|locale| locale.map(|locale| locale.name()))
+        };
+
+        assert_eq!(source(None).unwrap(), Some("sv_SE"));
+        assert_eq!(source(Some("da_DK")).unwrap(), Some("da_DK"));
+        assert!(matches!(
+            source(Some("en_US")),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn it_escapes_xml_special_characters() {
+        let mut xml = String::new();
+        push_xml_escaped(&mut xml, "<b>Tom & \"Jerry's\"</b>");
+
+        assert_eq!(xml, "&lt;b&gt;Tom &amp; &quot;Jerry&apos;s&quot;&lt;/b&gt;");
+    }
+}
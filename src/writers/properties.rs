@@ -0,0 +1,124 @@
+use super::{locale_tag, write_locales, TranslationWriter, WrittenFile};
+use crate::{get_file_location, Config, Error, Translations};
+use std::fmt::Write as _;
+use std::fs;
+
+/// Base name of the output files when `output_filename` isn't configured.
+const DEFAULT_BASE_NAME: &str = "messages";
+
+/// Writes one Java `.properties` file per locale, named `messages_<locale>.properties` (or
+/// `<output_filename>_<locale>.properties`) like a Java resource bundle, e.g. `messages_en_US.properties`.
+///
+/// By default files are written in ISO-8859-1, which every Java runtime reads, with characters
+/// outside of it escaped as `\uXXXX`. When `properties_utf8` is configured, files are written in
+/// UTF-8 instead, which Java 9 and newer read for resource bundles.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PropertiesWriter;
+
+impl TranslationWriter for PropertiesWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let base_name = config.output_filename.unwrap_or(DEFAULT_BASE_NAME);
+
+        write_locales(translations, |locale| {
+            let mut filename = get_file_location(config.output_dir)?;
+            filename.push(format!(
+                "{base_name}_{}.properties",
+                locale_tag(locale.name(), '_')
+            ));
+
+            let mut properties = String::new();
+            for (key, value) in locale.iter() {
+                escape(&mut properties, key, true, config.properties_utf8);
+                properties.push('=');
+                escape(&mut properties, value, false, config.properties_utf8);
+                properties.push('\n');
+            }
+
+            if config.properties_utf8 {
+                fs::write(&filename, properties).map_err(Error::file(&filename))?;
+            } else {
+                // Every character left after escaping is in ISO-8859-1, so is written as one byte.
+                let latin1 = properties
+                    .chars()
+                    .map(|char| char as u8)
+                    .collect::<Vec<u8>>();
+                fs::write(&filename, latin1).map_err(Error::file(&filename))?;
+            }
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
+
+/// Appends `text` to `output`, escaped as a `.properties` key or value. Backslashes, line breaks,
+/// tabs and the `=`, `:`, `#` and `!` separator and comment characters are always escaped, as is
+/// leading whitespace. Spaces are escaped anywhere in keys. Control characters, and characters
+/// outside ISO-8859-1 unless `utf8` is set, are escaped as `\uXXXX`.
+fn escape(output: &mut String, text: &str, is_key: bool, utf8: bool) {
+    for (idx, char) in text.chars().enumerate() {
+        match char {
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{c}' => output.push_str("\\f"),
+            '=' | ':' | '#' | '!' => {
+                output.push('\\');
+                output.push(char);
+            }
+            ' ' if idx == 0 || is_key => output.push_str("\\ "),
+            _ if char.is_control() || (!utf8 && char > '\u{ff}') => {
+                let mut units = [0; 2];
+                for unit in char.encode_utf16(&mut units) {
+                    let _ = write!(output, "\\u{unit:04X}");
+                }
+            }
+            _ => output.push(char),
+        }
+    }
+}
+
+#[cfg(test)]
+mod properties_tests {
+    use super::escape;
+    use pretty_assertions::assert_eq;
+
+    fn escaped(text: &str, is_key: bool, utf8: bool) -> String {
+        let mut output = String::new();
+        escape(&mut output, text, is_key, utf8);
+        output
+    }
+
+    #[test]
+    fn it_escapes_separators_and_leading_whitespace() {
+        assert_eq!(
+            escaped("  a=b: #1 !", false, false),
+            "\\  a\\=b\\: \\#1 \\!"
+        );
+        assert_eq!(escaped("app title:x", true, false), "app\\ title\\:x");
+        assert_eq!(
+            escaped("C:\\path\n\tnext", false, false),
+            "C\\:\\\\path\\n\\tnext"
+        );
+    }
+
+    #[test]
+    fn it_escapes_characters_outside_latin_1_unless_writing_utf_8() {
+        assert_eq!(escaped("Où ça", false, false), "Où ça");
+        assert_eq!(
+            escaped("日本 €5 😀", false, false),
+            "\\u65E5\\u672C \\u20AC5 \\uD83D\\uDE00"
+        );
+        assert_eq!(escaped("日本 €5 😀", false, true), "日本 €5 😀");
+        assert_eq!(escaped("bell\u{7}", false, true), "bell\\u0007");
+    }
+}
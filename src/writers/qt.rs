@@ -0,0 +1,182 @@
+use super::{
+    locale_tag, push_xml_escaped, source_locale, write_locales, TranslationWriter, WrittenFile,
+};
+use crate::translations::LocaleTranslations;
+use crate::{get_file_location, Config, Error, Translations};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Prefix of the output files when `output_filename` isn't configured.
+const DEFAULT_PREFIX: &str = "app";
+
+/// Start of every `.ts` file, as written by `lupdate`.
+const TS_HEADER: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE TS>\n";
+
+/// Writes one Qt Linguist translation source file per locale, named `app_<locale>.ts` (or
+/// `<output_filename>_<locale>.ts`) with `en-US` style locales written as `en_US`.
+///
+/// Keys are grouped into `<context>` elements by the context column, or else by the key before
+/// its first `.`, so `login.title` is in the `login` context. Every message has its key as its
+/// `id`, and its value in the source locale (the configured source locale, or else the first
+/// locale) as its `<source>`, falling back to the key when it has no value in the source locale.
+/// Empty values are written as unfinished translations, which Qt Linguist lists as needing
+/// translation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QtWriter;
+
+impl TranslationWriter for QtWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let prefix = config.output_filename.unwrap_or(DEFAULT_PREFIX);
+        let source = source_locale(translations, config)?;
+        let contexts = contexts(translations);
+
+        write_locales(translations, |locale| {
+            let mut filename = get_file_location(config.output_dir)?;
+            filename.push(format!("{prefix}_{}.ts", locale_tag(locale.name(), '_')));
+            fs::write(&filename, write_ts(&contexts, locale, source, translations))
+                .map_err(Error::file(&filename))?;
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
+
+/// Groups every translation key by the context it is written in, ordered by context name. Keys
+/// without a context or a `.` are in the unnamed context.
+fn contexts(translations: &Translations) -> BTreeMap<&str, Vec<&str>> {
+    let mut contexts = BTreeMap::<&str, Vec<&str>>::new();
+
+    for key in translations.keys() {
+        let context = translations
+            .context(key)
+            .unwrap_or_else(|| key.split_once('.').map_or("", |(prefix, _)| prefix));
+        contexts.entry(context).or_default().push(key);
+    }
+
+    contexts
+}
+
+/// Writes every key, with its translation in `locale`, as a Qt Linguist `.ts` file.
+fn write_ts(
+    contexts: &BTreeMap<&str, Vec<&str>>,
+    locale: LocaleTranslations,
+    source: Option<LocaleTranslations>,
+    translations: &Translations,
+) -> String {
+    let mut ts = String::from(TS_HEADER);
+    ts.push_str("<TS version=\"2.1\" language=\"");
+    push_xml_escaped(&mut ts, &locale_tag(locale.name(), '_'));
+    if let Some(source) = source {
+        ts.push_str("\" sourcelanguage=\"");
+        push_xml_escaped(&mut ts, &locale_tag(source.name(), '_'));
+    }
+    ts.push_str("\">\n");
+
+    for (context, keys) in contexts {
+        ts.push_str("<context>\n    <name>");
+        push_xml_escaped(&mut ts, context);
+        ts.push_str("</name>\n");
+
+        for key in keys {
+            let source_text = source
+                .and_then(|source| source.get(key))
+                .filter(|text| !text.is_empty())
+                .unwrap_or(key);
+            ts.push_str("    <message id=\"");
+            push_xml_escaped(&mut ts, key);
+            ts.push_str("\">\n        <source>");
+            push_xml_escaped(&mut ts, source_text);
+            ts.push_str("</source>\n");
+            if let Some(description) = translations.description(key) {
+                ts.push_str("        <extracomment>");
+                push_xml_escaped(&mut ts, description);
+                ts.push_str("</extracomment>\n");
+            }
+            match locale.get(key).filter(|value| !value.is_empty()) {
+                Some(value) => {
+                    ts.push_str("        <translation>");
+                    push_xml_escaped(&mut ts, value);
+                    ts.push_str("</translation>\n");
+                }
+                None => ts.push_str("        <translation type=\"unfinished\"></translation>\n"),
+            }
+            ts.push_str("    </message>\n");
+        }
+
+        ts.push_str("</context>\n");
+    }
+
+    ts.push_str("</TS>\n");
+    ts
+}
+
+#[cfg(test)]
+mod qt_tests {
+    use super::{contexts, write_ts};
+    use crate::{DuplicatePolicy, Translations};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn it_groups_keys_by_context_column_or_key_prefix() {
+        let mut translations = Translations::default();
+        for key in ["login.title", "login.submit", "quit", "save"] {
+            let key_idx = translations.add_key(key, 2);
+            translations.insert("en_US", key_idx, key, DuplicatePolicy::LastWins);
+            if key == "save" {
+                translations.set_context(key_idx, "Editor");
+            }
+        }
+
+        assert_eq!(
+            contexts(&translations).into_iter().collect::<Vec<_>>(),
+            [
+                ("", vec!["quit"]),
+                ("Editor", vec!["save"]),
+                ("login", vec!["login.submit", "login.title"])
+            ]
+        );
+    }
+
+    #[test]
+    fn it_writes_source_text_and_unfinished_translations() {
+        let mut translations = Translations::default();
+        for (key, en, de) in [
+            ("menu.open", "Open <file>", "Öffnen"),
+            ("menu.quit", "Quit", ""),
+            ("menu.new", "", ""),
+        ] {
+            let key_idx = translations.add_key(key, 2);
+            translations.insert("en", key_idx, en, DuplicatePolicy::LastWins);
+            translations.insert("de-DE", key_idx, de, DuplicatePolicy::LastWins);
+        }
+        let contexts = contexts(&translations);
+
+        assert_eq!(
+            write_ts(
+                &contexts,
+                translations.locale("de-DE").unwrap(),
+                translations.locale("en"),
+                &translations
+            ),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE TS>\n\
+             <TS version=\"2.1\" language=\"de_DE\" sourcelanguage=\"en\">\n\
+             <context>\n    <name>menu</name>\n\
+             \x20   <message id=\"menu.new\">\n        <source>menu.new</source>\n\
+             \x20       <translation type=\"unfinished\"></translation>\n    </message>\n\
+             \x20   <message id=\"menu.open\">\n        <source>Open &lt;file&gt;</source>\n\
+             \x20       <translation>Öffnen</translation>\n    </message>\n\
+             \x20   <message id=\"menu.quit\">\n        <source>Quit</source>\n\
+             \x20       <translation type=\"unfinished\"></translation>\n    </message>\n\
+             </context>\n</TS>\n"
+        );
+    }
+}
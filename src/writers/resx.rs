@@ -0,0 +1,73 @@
+use super::{locale_tag, push_xml_escaped, write_locales, TranslationWriter, WrittenFile};
+use crate::{get_file_location, Config, Error, Translations};
+use std::fs;
+
+/// Base name of the output files when `output_filename` isn't configured.
+const DEFAULT_BASE_NAME: &str = "Strings";
+
+/// Headers identifying the file as a version 2.0 RESX file, as written by Visual Studio.
+const RESX_HEADER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<root>
+  <resheader name="resmimetype">
+    <value>text/microsoft-resx</value>
+  </resheader>
+  <resheader name="version">
+    <value>2.0</value>
+  </resheader>
+  <resheader name="reader">
+    <value>System.Resources.ResXResourceReader, System.Windows.Forms, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089</value>
+  </resheader>
+  <resheader name="writer">
+    <value>System.Resources.ResXResourceWriter, System.Windows.Forms, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089</value>
+  </resheader>
+"#;
+
+/// Writes one .NET RESX resource file per locale, named `Strings.<culture>.resx` (or
+/// `<output_filename>.<culture>.resx`) with `en_US` style locales written as `en-US` cultures.
+///
+/// Each key is written as a `<data>` entry, with the key's description from the description
+/// column as its `<comment>`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ResxWriter;
+
+impl TranslationWriter for ResxWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let base_name = config.output_filename.unwrap_or(DEFAULT_BASE_NAME);
+
+        write_locales(translations, |locale| {
+            let mut filename = get_file_location(config.output_dir)?;
+            filename.push(format!(
+                "{base_name}.{}.resx",
+                locale_tag(locale.name(), '-')
+            ));
+
+            let mut resx = String::from(RESX_HEADER);
+            for (key, value) in locale.iter() {
+                resx.push_str("  <data name=\"");
+                push_xml_escaped(&mut resx, key);
+                resx.push_str("\" xml:space=\"preserve\">\n    <value>");
+                push_xml_escaped(&mut resx, value);
+                resx.push_str("</value>\n");
+                if let Some(description) = translations.description(key) {
+                    resx.push_str("    <comment>");
+                    push_xml_escaped(&mut resx, description);
+                    resx.push_str("</comment>\n");
+                }
+                resx.push_str("  </data>\n");
+            }
+            resx.push_str("</root>\n");
+            fs::write(&filename, resx).map_err(Error::file(&filename))?;
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
@@ -0,0 +1,272 @@
+use super::{locale_tag, source_locale, write_locales, TranslationWriter, WrittenFile};
+use crate::translations::LocaleTranslations;
+use crate::{get_file_location, Config, Error, Translations};
+use serde::{Serialize, Serializer};
+use serde_json::to_string_pretty;
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+
+/// Writes the `_locales/<locale>/messages.json` files of a Chrome extension or WebExtension,
+/// with `en-US` style locales written as `en_US`. `output_filename` isn't used, as browsers
+/// only read files named `messages.json`.
+///
+/// Each key is written as `{ "message": "...", "description": "..." }`, with its description
+/// from the description column. Keys are converted to the `[A-Za-z0-9_]` names browsers allow,
+/// and `$NAME$` placeholders in each message are listed with their position as their content.
+/// Positions follow the order placeholders appear in the source locale (the configured source
+/// locale, or else the first locale), so `$1` is the same substitution in every locale.
+/// Placeholders which aren't in the source message are numbered after those which are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WebExtensionWriter;
+
+/// A locale's translations, serialized as a map of message name to message.
+struct Messages<'a> {
+    names: &'a BTreeMap<String, &'a str>,
+    locale: LocaleTranslations<'a>,
+    source: Option<LocaleTranslations<'a>>,
+    translations: &'a Translations,
+}
+
+impl Serialize for Messages<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.names.iter().filter_map(|(name, key)| {
+            let message = self.locale.get(key)?;
+            Some((
+                name,
+                Message {
+                    message,
+                    description: self.translations.description(key),
+                    placeholders: Placeholders(placeholder_order(
+                        message,
+                        self.source.and_then(|source| source.get(key)),
+                    )),
+                },
+            ))
+        }))
+    }
+}
+
+/// A single message, with its description and placeholders.
+#[derive(Serialize)]
+struct Message<'a> {
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Placeholders::is_empty")]
+    placeholders: Placeholders,
+}
+
+/// Lower case names of the placeholders in a message, serialized as an object with the
+/// position of each as its content, e.g. `{ "user": { "content": "$1" } }`.
+struct Placeholders(Vec<String>);
+
+impl Placeholders {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[derive(Serialize)]
+struct Placeholder {
+    content: String,
+}
+
+impl Serialize for Placeholders {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().enumerate().map(|(idx, name)| {
+            let content = format!("${}", idx + 1);
+            (name, Placeholder { content })
+        }))
+    }
+}
+
+impl TranslationWriter for WebExtensionWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let names = message_names(translations)?;
+        let source = source_locale(translations, config)?;
+
+        write_locales(translations, |locale| {
+            let mut filename = get_file_location(config.output_dir)?;
+            filename.push("_locales");
+            filename.push(locale_tag(locale.name(), '_'));
+            create_dir_all(&filename).map_err(Error::file(&filename))?;
+            filename.push("messages.json");
+
+            let json = to_string_pretty(&Messages {
+                names: &names,
+                locale,
+                source,
+                translations,
+            })?;
+            writeln!(
+                File::create(&filename).map_err(Error::file(&filename))?,
+                "{json}"
+            )?;
+
+            Ok(WrittenFile {
+                path: filename,
+                locale: Some(locale.name().to_owned()),
+                keys: locale.len(),
+                omitted: locale.omitted(),
+            })
+        })
+    }
+}
+
+/// Converts every translation key into a message name, replacing characters other than
+/// `[A-Za-z0-9_]` with `_`. Returns an error if two keys would have the same name, which
+/// browsers compare without case.
+fn message_names(translations: &Translations) -> Result<BTreeMap<String, &str>, Error> {
+    let mut names = BTreeMap::new();
+    let mut lower_case_names = BTreeMap::new();
+
+    for key in translations.keys() {
+        let name = key
+            .chars()
+            .map(|char| match char {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => char,
+                _ => '_',
+            })
+            .collect::<String>();
+
+        if let Some(existing) = lower_case_names.insert(name.to_ascii_lowercase(), key) {
+            return Err(Error::InvalidKey {
+                key: key.to_owned(),
+                reason: format!(
+                    "it is written as the same message name as \"{existing}\" (`{name}`)."
+                ),
+            });
+        }
+        names.insert(name, key);
+    }
+
+    Ok(names)
+}
+
+/// Orders the placeholders of a message as they first appear in its source message, followed
+/// by any the source message doesn't use, in the order they appear in the message.
+fn placeholder_order(message: &str, source_message: Option<&str>) -> Vec<String> {
+    let mut names = source_message.map_or_else(Vec::new, placeholders);
+    for name in placeholders(message) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Finds the names of the `$NAME$` placeholders in a message, lower cased and in order of first
+/// use. `$$` is an escaped `$`, and `$1` style substitutions aren't named placeholders.
+fn placeholders(message: &str) -> Vec<String> {
+    let mut names = Vec::<String>::new();
+    let mut rest = message;
+
+    while let Some(start) = rest.find('$') {
+        let after = &rest[start + 1..];
+        let name = after.find('$').map(|end| &after[..end]).filter(|name| {
+            name.starts_with(|char: char| char.is_ascii_alphabetic() || char == '_')
+                && name
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '_')
+        });
+
+        match name {
+            Some(name) => {
+                let name = name.to_ascii_lowercase();
+                rest = &after[name.len() + 1..];
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            // Skip both characters of an escaped `$$`.
+            None if after.starts_with('$') => rest = &after[1..],
+            None => rest = after,
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod webextension_tests {
+    use super::{message_names, placeholder_order, placeholders, Messages};
+    use crate::{DuplicatePolicy, Error, Translations};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn it_finds_named_placeholders() {
+        assert_eq!(
+            placeholders("Hi $USER$, $COUNT$ new for $user$. Costs $$5 or $1"),
+            ["user", "count"]
+        );
+        assert_eq!(placeholders("$ not $a placeholder"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn it_numbers_placeholders_in_the_order_of_the_source_locale() {
+        let mut translations = Translations::default();
+        let key_idx = translations.add_key("welcome", 2);
+        for (locale, value) in [
+            ("en_US", "Hello $USER$, you have $COUNT$ messages"),
+            ("ja_JP", "$COUNT$ 件のメッセージ、$USER$ さん $EXTRA$"),
+        ] {
+            translations.insert(locale, key_idx, value, DuplicatePolicy::LastWins);
+        }
+        let names = message_names(&translations).unwrap();
+        let messages = |locale| {
+            serde_json::to_value(Messages {
+                names: &names,
+                locale: translations.locale(locale).unwrap(),
+                source: translations.first_locale(),
+                translations: &translations,
+            })
+            .unwrap()
+        };
+
+        assert_eq!(
+            messages("ja_JP")["welcome"]["placeholders"],
+            serde_json::json!({
+                "user": { "content": "$1" },
+                "count": { "content": "$2" },
+                "extra": { "content": "$3" }
+            })
+        );
+        assert_eq!(
+            messages("en_US")["welcome"]["placeholders"],
+            serde_json::json!({
+                "user": { "content": "$1" },
+                "count": { "content": "$2" }
+            })
+        );
+        assert_eq!(placeholder_order("$B$ $A$", None), ["b", "a"]);
+    }
+
+    #[test]
+    fn it_sanitizes_message_names_and_detects_collisions() {
+        let mut translations = Translations::default();
+        for key in ["app.title", "app-name"] {
+            let key_idx = translations.add_key(key, 2);
+            translations.insert("en_US", key_idx, "text", DuplicatePolicy::LastWins);
+        }
+        let names = message_names(&translations).unwrap();
+
+        assert_eq!(
+            names.into_iter().collect::<Vec<_>>(),
+            [
+                ("app_name".into(), "app-name"),
+                ("app_title".into(), "app.title")
+            ]
+        );
+
+        translations.add_key("App_Title", 3);
+        assert!(matches!(
+            message_names(&translations),
+            Err(Error::InvalidKey { key, .. }) if key == "app.title"
+        ));
+    }
+}
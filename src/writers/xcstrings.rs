@@ -0,0 +1,358 @@
+use super::{
+    locale_tag, push_xml_escaped, source_locale, write_locales, TranslationWriter, WrittenFile,
+};
+use crate::translations::LocaleTranslations;
+use crate::{get_file_location, Config, Error, Translations};
+use serde::Serialize;
+use serde_json::to_string_pretty;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, create_dir_all, File};
+use std::io::Write;
+
+/// Table name of the output files when `output_filename` isn't configured.
+const DEFAULT_TABLE: &str = "Localizable";
+
+/// CLDR plural categories, which keys can end with to be written as plural variants.
+const PLURAL_CATEGORIES: [&str; 6] = ["zero", "one", "two", "few", "many", "other"];
+
+/// Start of every `.stringsdict` property list.
+const PLIST_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+"#;
+
+/// Writes an Xcode String Catalog, `Localizable.xcstrings` (or `<output_filename>.xcstrings`),
+/// containing every locale, with `en_US` style locales written as `en-US`.
+///
+/// Keys ending in a plural category, such as `items.one` and `items.other`, are written as the
+/// plural variants of a single string, `items`, when an `.other` variant exists. Each string
+/// has the description of its key as its comment. For targets older than Xcode 15, the plural
+/// strings of each locale are also written to `<locale>.lproj/Localizable.stringsdict`.
+///
+/// The source language of the catalog is the configured source locale, or else the locale of the
+/// first locale column.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct XcstringsWriter;
+
+/// A string in the catalog, and the translation keys holding its value or plural variants.
+#[derive(Debug)]
+enum Entry<'a> {
+    Single(&'a str),
+    Plural(BTreeMap<&'static str, &'a str>),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the description of the entry's key, or of its first plural variant with one.
+    fn comment(&self, translations: &'a Translations) -> Option<&'a str> {
+        match self {
+            Entry::Single(key) => translations.description(key),
+            Entry::Plural(variants) => variants
+                .values()
+                .find_map(|key| translations.description(key)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Catalog<'a> {
+    source_language: String,
+    strings: BTreeMap<&'a str, CatalogString<'a>>,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CatalogString<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<&'a str>,
+    extraction_state: &'static str,
+    localizations: BTreeMap<String, Localization<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Localization<'a> {
+    StringUnit(StringUnit<'a>),
+    Variations {
+        plural: BTreeMap<&'static str, Variation<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Variation<'a> {
+    string_unit: StringUnit<'a>,
+}
+
+#[derive(Serialize)]
+struct StringUnit<'a> {
+    state: &'static str,
+    value: &'a str,
+}
+
+impl<'a> StringUnit<'a> {
+    /// Empty values are marked as new, so Xcode lists them as needing translation.
+    fn new(value: &'a str) -> StringUnit<'a> {
+        let state = if value.is_empty() {
+            "new"
+        } else {
+            "translated"
+        };
+        StringUnit { state, value }
+    }
+}
+
+impl TranslationWriter for XcstringsWriter {
+    fn write(
+        &self,
+        translations: &Translations,
+        config: &Config,
+    ) -> Result<Vec<WrittenFile>, Error> {
+        let table = config.output_filename.unwrap_or(DEFAULT_TABLE);
+        let entries = entries(translations)?;
+        let source_language =
+            source_locale(translations, config)?.map_or("en", |locale| locale.name());
+
+        let strings = entries
+            .iter()
+            .map(|(name, entry)| {
+                let localizations = translations
+                    .locales()
+                    .filter_map(|locale| {
+                        Some((locale_tag(locale.name(), '-'), localization(entry, locale)?))
+                    })
+                    .collect();
+                let string = CatalogString {
+                    comment: entry.comment(translations),
+                    extraction_state: "manual",
+                    localizations,
+                };
+                (*name, string)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut filename = get_file_location(config.output_dir)?;
+        filename.push(format!("{table}.xcstrings"));
+        let keys = strings.len();
+        let json = to_string_pretty(&Catalog {
+            source_language: locale_tag(source_language, '-'),
+            strings,
+            version: "1.0",
+        })?;
+        writeln!(
+            File::create(&filename).map_err(Error::file(&filename))?,
+            "{json}"
+        )?;
+
+        let mut written = vec![WrittenFile {
+            path: filename,
+            locale: None,
+            keys,
+            omitted: 0,
+        }];
+        if entries
+            .values()
+            .any(|entry| matches!(entry, Entry::Plural(_)))
+        {
+            written.extend(write_locales(translations, |locale| {
+                write_stringsdict(&entries, locale, table, config)
+            })?);
+        }
+
+        Ok(written)
+    }
+}
+
+/// Groups every translation key into the catalog string it is written as, ordered by name.
+/// Returns an error if a key is written as the same string as a plural key's variants.
+fn entries(translations: &Translations) -> Result<BTreeMap<&str, Entry<'_>>, Error> {
+    let keys = translations.keys().collect::<BTreeSet<&str>>();
+    let mut entries = BTreeMap::new();
+
+    for key in translations.keys() {
+        let plural = key.rsplit_once('.').and_then(|(name, category)| {
+            let category = PLURAL_CATEGORIES.iter().find(|known| **known == category)?;
+            keys.contains(format!("{name}.other").as_str())
+                .then_some((name, *category))
+        });
+
+        let conflict = match plural {
+            Some((name, category)) => match entries
+                .entry(name)
+                .or_insert_with(|| Entry::Plural(BTreeMap::new()))
+            {
+                Entry::Plural(variants) => {
+                    variants.insert(category, key);
+                    None
+                }
+                Entry::Single(existing) => Some(*existing),
+            },
+            None => match entries.insert(key, Entry::Single(key)) {
+                Some(Entry::Plural(variants)) => variants.values().next().copied(),
+                _ => None,
+            },
+        };
+
+        if let Some(existing) = conflict {
+            return Err(Error::InvalidKey {
+                key: key.to_owned(),
+                reason: format!("it is written as the same catalog string as \"{existing}\"."),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Returns the localization of an entry in `locale`, if it has any values in the locale.
+fn localization<'a>(entry: &Entry, locale: LocaleTranslations<'a>) -> Option<Localization<'a>> {
+    match entry {
+        Entry::Single(key) => Some(Localization::StringUnit(StringUnit::new(locale.get(key)?))),
+        Entry::Plural(variants) => {
+            let plural = variants
+                .iter()
+                .filter_map(|(category, key)| {
+                    let string_unit = StringUnit::new(locale.get(key)?);
+                    Some((*category, Variation { string_unit }))
+                })
+                .collect::<BTreeMap<_, _>>();
+            (!plural.is_empty()).then_some(Localization::Variations { plural })
+        }
+    }
+}
+
+/// Writes the plural strings of `locale` to `<locale>.lproj/<table>.stringsdict`.
+fn write_stringsdict(
+    entries: &BTreeMap<&str, Entry>,
+    locale: LocaleTranslations,
+    table: &str,
+    config: &Config,
+) -> Result<WrittenFile, Error> {
+    let mut filename = get_file_location(config.output_dir)?;
+    filename.push(format!("{}.lproj", locale_tag(locale.name(), '-')));
+    create_dir_all(&filename).map_err(Error::file(&filename))?;
+    filename.push(format!("{table}.stringsdict"));
+
+    let mut plist = String::from(PLIST_HEADER);
+    let mut keys = 0;
+    let mut omitted = 0;
+    for (name, entry) in entries {
+        let variants = match entry {
+            Entry::Plural(variants) => variants
+                .iter()
+                .filter_map(|(category, key)| Some((*category, locale.get(key)?)))
+                .collect::<Vec<_>>(),
+            Entry::Single(_) => continue,
+        };
+        if variants.is_empty() {
+            omitted += 1;
+            continue;
+        }
+        keys += 1;
+
+        push_plist_key(&mut plist, 1, name);
+        plist.push_str("\t<dict>\n");
+        push_plist_key(&mut plist, 2, "NSStringLocalizedFormatKey");
+        plist.push_str("\t\t<string>%#@value@</string>\n");
+        push_plist_key(&mut plist, 2, "value");
+        plist.push_str("\t\t<dict>\n");
+        push_plist_key(&mut plist, 3, "NSStringFormatSpecTypeKey");
+        plist.push_str("\t\t\t<string>NSStringPluralRuleType</string>\n");
+        push_plist_key(&mut plist, 3, "NSStringFormatValueTypeKey");
+        plist.push_str("\t\t\t<string>");
+        plist.push_str(format_value_type(variants.iter().map(|(_, value)| *value)));
+        plist.push_str("</string>\n");
+        for (category, value) in variants {
+            push_plist_key(&mut plist, 3, category);
+            plist.push_str("\t\t\t<string>");
+            push_xml_escaped(&mut plist, value);
+            plist.push_str("</string>\n");
+        }
+        plist.push_str("\t\t</dict>\n\t</dict>\n");
+    }
+    plist.push_str("</dict>\n</plist>\n");
+    fs::write(&filename, plist).map_err(Error::file(&filename))?;
+
+    Ok(WrittenFile {
+        path: filename,
+        locale: Some(locale.name().to_owned()),
+        keys,
+        omitted,
+    })
+}
+
+/// Appends a `<key>` element indented by `depth` tabs.
+fn push_plist_key(plist: &mut String, depth: usize, key: &str) {
+    plist.push_str(&"\t".repeat(depth));
+    plist.push_str("<key>");
+    push_xml_escaped(plist, key);
+    plist.push_str("</key>\n");
+}
+
+/// Finds the integer format specifier, such as `lld` in `%lld items`, used by plural variants.
+/// Defaults to `lld`, the specifier Xcode uses for `Int`.
+fn format_value_type<'v>(mut values: impl Iterator<Item = &'v str>) -> &'static str {
+    values
+        .find_map(|value| {
+            value.match_indices('%').find_map(|(idx, _)| {
+                let spec = &value[idx + 1..];
+                ["lld", "ld", "d", "llu", "lu", "u", "i"]
+                    .into_iter()
+                    .find(|known| spec.starts_with(known))
+            })
+        })
+        .unwrap_or("lld")
+}
+
+#[cfg(test)]
+mod xcstrings_tests {
+    use super::{entries, format_value_type, Entry};
+    use crate::{DuplicatePolicy, Error, Translations};
+    use pretty_assertions::assert_eq;
+
+    fn translations(keys: &[&str]) -> Translations {
+        let mut translations = Translations::default();
+        for key in keys {
+            let key_idx = translations.add_key(key, 2);
+            translations.insert("en_US", key_idx, key, DuplicatePolicy::LastWins);
+        }
+        translations
+    }
+
+    #[test]
+    fn it_groups_plural_variants_which_have_an_other_variant() {
+        let translations = translations(&["items.one", "items.other", "menu.one", "title"]);
+        let entries = entries(&translations).unwrap();
+
+        assert_eq!(
+            entries.keys().copied().collect::<Vec<_>>(),
+            ["items", "menu.one", "title"]
+        );
+        assert!(matches!(
+            &entries["items"],
+            Entry::Plural(variants) if variants.len() == 2 && variants["one"] == "items.one"
+        ));
+        assert!(matches!(entries["menu.one"], Entry::Single("menu.one")));
+    }
+
+    #[test]
+    fn it_returns_an_error_for_keys_written_as_the_same_string() {
+        let translations = translations(&["items", "items.other"]);
+
+        assert!(matches!(
+            entries(&translations),
+            Err(Error::InvalidKey { key, .. }) if key == "items.other"
+        ));
+    }
+
+    #[test]
+    fn it_finds_the_format_specifier_of_plural_variants() {
+        assert_eq!(
+            format_value_type(["One item", "%ld items"].into_iter()),
+            "ld"
+        );
+        assert_eq!(format_value_type(["100%% done"].into_iter()), "lld");
+    }
+}
@@ -1,9 +1,42 @@
 use assert_cmd::Command;
+use predicates::prelude::*;
 
 fn get_bin() -> Command {
     Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap()
 }
 
+/// Converts `csv` with the binary in a temporary directory named after `name`, passing `args`
+/// before the output directory and the input file. Returns the contents of each of `files`, as
+/// paths relative to the output directory, or `None` for files which weren't written.
+fn convert_csv<const N: usize>(
+    name: &str,
+    csv: &str,
+    args: &[&str],
+    files: [&str; N],
+) -> [Option<Vec<u8>>; N] {
+    let dir = std::env::temp_dir().join(format!("translocate_{name}_test"));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("input.csv");
+    std::fs::write(&input, csv).unwrap();
+
+    get_bin()
+        .args(args)
+        .arg("-o")
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success();
+    let written = files.map(|file| std::fs::read(dir.join(file)).ok());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    written
+}
+
+/// Returns the text of a file returned by [`convert_csv`], which must have been written.
+fn text(file: &Option<Vec<u8>>) -> &str {
+    std::str::from_utf8(file.as_deref().expect("file should be written")).unwrap()
+}
+
 #[test]
 fn it_prints_version_info_successfully() {
     get_bin()
@@ -31,3 +64,474 @@ fn it_has_special_char_warning() {
         .stdout(predicates::str::contains("Warning:"))
         .failure();
 }
+
+#[test]
+fn it_keeps_special_char_warnings_out_of_a_json_report() {
+    let dir = std::env::temp_dir().join("translocate_report_path_test");
+    std::fs::create_dir_all(dir.join("$out")).unwrap();
+    let input = dir.join("input.csv");
+    std::fs::write(&input, "id,en_US\napp.title,Hello\n").unwrap();
+
+    let output = get_bin()
+        .args(["--report", "json", "-o"])
+        .arg(dir.join("$out"))
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["locales"][0]["keys"], 1);
+}
+
+#[test]
+fn it_reports_invalid_terminator_as_an_error() {
+    get_bin()
+        .args(["-t", "newline", "Cargo.toml"])
+        .assert()
+        .stderr(predicates::str::contains("invalid configuration"))
+        .failure();
+}
+
+#[test]
+fn it_names_a_missing_output_directory_rather_than_the_input_file() {
+    let dir = std::env::temp_dir().join("translocate_missing_output_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("input.csv");
+    std::fs::write(&input, "id,en_US\napp.title,Hello\n").unwrap();
+
+    let assert = get_bin()
+        .arg("-o")
+        .arg(dir.join("missing"))
+        .arg(&input)
+        .assert()
+        .failure();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert
+        .stderr(predicates::str::contains("en_US.json"))
+        .stderr(predicates::str::contains("not found.").not());
+}
+
+#[test]
+fn it_prints_a_json_report() {
+    let dir = std::env::temp_dir().join("translocate_report_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("report.csv");
+    std::fs::write(
+        &input,
+        "id,en_US,\napp.title,Title,\napp.title,New title,\n",
+    )
+    .unwrap();
+
+    let output = get_bin()
+        .args(["--report", "json", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report["rows"], 2);
+    assert_eq!(report["delimiter"], ",");
+    assert_eq!(report["locales"][0]["locale"], "en_US");
+    assert_eq!(report["locales"][0]["keys"], 1);
+    assert_eq!(report["duplicate_keys"][0]["key"], "app.title");
+}
+
+#[test]
+fn it_counts_rows_using_the_configured_delimiter_and_terminator() {
+    let dir = std::env::temp_dir().join("translocate_single_pass_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("single_pass.csv");
+    std::fs::write(&input, "id;en_US|one;One|two;Two|three;Three|").unwrap();
+
+    let output = get_bin()
+        .args(["--report", "json", "-d", ";", "-t", "124", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report["rows"], 3);
+    assert_eq!(report["locales"][0]["keys"], 3);
+}
+
+#[test]
+#[cfg(feature = "spreadsheet")]
+fn it_converts_the_selected_sheet_of_a_spreadsheet() {
+    let dir = std::env::temp_dir().join("translocate_sheet_test");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let output = get_bin()
+        .args(["--report", "json", "--sheet", "errors", "-o"])
+        .arg(&dir)
+        .arg("tests/fixtures/translations.xlsx")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let en_us = std::fs::read_to_string(dir.join("en_US.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report["rows"], 2);
+    assert_eq!(report["locales"][1]["locale"], "fr_FR");
+    assert!(en_us.contains("\"error.server\": \"Server error\""));
+}
+
+#[test]
+fn it_transcodes_windows_1252_input_unless_an_encoding_is_given() {
+    let dir = std::env::temp_dir().join("translocate_encoding_app_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("cp1252.csv");
+    std::fs::write(&input, b"id,fr_FR\nfaq,O\xF9 est-il ?\n").unwrap();
+
+    get_bin().arg("-o").arg(&dir).arg(&input).assert().success();
+    let fr_fr = std::fs::read_to_string(dir.join("fr_FR.json")).unwrap();
+    get_bin()
+        .args(["--encoding", "iso-8859-15", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success();
+    let latin9 = std::fs::read_to_string(dir.join("fr_FR.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(fr_fr.contains("\"faq\": \"Où est-il ?\""));
+    assert!(latin9.contains("\"faq\": \"Où est-il ?\""));
+}
+
+#[test]
+fn it_detects_and_reports_the_delimiter() {
+    let dir = std::env::temp_dir().join("translocate_dialect_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("semicolons.csv");
+    std::fs::write(&input, "id;en_US;fr_FR\r\nfaq;Help, please;Aide\r\n").unwrap();
+
+    let output = get_bin()
+        .args(["--report", "json", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let en_us = std::fs::read_to_string(dir.join("en_US.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report["delimiter"], ";");
+    assert_eq!(report["detected_dialect"]["line_ending"], "CRLF");
+    assert!(en_us.contains("\"faq\": \"Help, please\""));
+}
+
+#[test]
+fn it_keeps_double_quotes_inside_detected_single_quoted_cells() {
+    let dir = std::env::temp_dir().join("translocate_single_quote_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("single_quotes.csv");
+    std::fs::write(
+        &input,
+        "id;en_US\nquote;'He said \"hi\" ok'\nlist;'one; two'\n",
+    )
+    .unwrap();
+
+    get_bin().arg("-o").arg(&dir).arg(&input).assert().success();
+    let en_us = std::fs::read_to_string(dir.join("en_US.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(en_us.contains(r#""quote": "He said \"hi\" ok""#));
+    assert!(en_us.contains(r#""list": "one; two""#));
+}
+
+#[test]
+fn it_writes_typed_values_by_column_key_suffix_and_type_column() {
+    let dir = std::env::temp_dir().join("translocate_typed_values_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("typed.csv");
+    std::fs::write(
+        &input,
+        "id,type,en_US,fr_FR\n\
+         months[],,\"[\"\"Jan\"\", \"\"Feb\"\"]\",\"[\"\"janv.\"\", \"\"févr.\"\"]\"\n\
+         limit,number,1.50,2\n\
+         beta,boolean,TRUE,false\n\
+         count,,3,3\n",
+    )
+    .unwrap();
+
+    get_bin()
+        .args(["--type-column", "type", "--typed-columns", "fr_FR", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success();
+    let en_us = std::fs::read_to_string(dir.join("en_US.json")).unwrap();
+    let fr_fr = std::fs::read_to_string(dir.join("fr_FR.json")).unwrap();
+    let type_written = dir.join("type.json").exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        en_us,
+        "{\n  \"beta\": true,\n  \"count\": \"3\",\n  \"limit\": 1.50,\n  \"months\": [\"Jan\", \"Feb\"]\n}\n"
+    );
+    assert!(fr_fr.contains("\"count\": 3"));
+    assert!(!type_written);
+}
+
+#[test]
+fn it_writes_keys_ending_in_brackets_as_json_without_typed_options() {
+    let [en_us] = convert_csv(
+        "bracket_keys",
+        "id,en_US\nmonths[],\"[\"\"Jan\"\", \"\"Feb\"\"]\"\ncount,3\n",
+        &[],
+        ["en_US.json"],
+    );
+
+    assert_eq!(
+        text(&en_us),
+        "{\n  \"count\": \"3\",\n  \"months\": [\"Jan\", \"Feb\"]\n}\n"
+    );
+}
+
+#[test]
+fn it_omits_empty_values_and_reports_how_many_were_omitted() {
+    let dir = std::env::temp_dir().join("translocate_omit_empty_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("partial.csv");
+    std::fs::write(
+        &input,
+        "id,en_US,fr_FR\napp.title,Hello,Bonjour\napp.close,Close,\n",
+    )
+    .unwrap();
+
+    let output = get_bin()
+        .args(["--omit-empty", "--report", "json", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let fr_fr = std::fs::read_to_string(dir.join("fr_FR.json")).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(fr_fr, "{\n  \"app.title\": \"Bonjour\"\n}\n");
+    assert_eq!(report["locales"][0]["omitted_keys"], 0);
+    assert_eq!(report["locales"][1]["omitted_keys"], 1);
+    assert_eq!(report["locales"][1]["keys"], 1);
+}
+
+#[test]
+fn it_writes_java_properties_files() {
+    let csv = "id,en-US,ja_JP\napp.title,Où: here,どこ\napp.price,= 5 €,5 €\n";
+
+    let [en_us, ja_jp] = convert_csv(
+        "properties",
+        csv,
+        &["--format", "properties"],
+        ["messages_en_US.properties", "messages_ja_JP.properties"],
+    );
+    let [utf8] = convert_csv(
+        "properties_utf8",
+        csv,
+        &[
+            "--format",
+            "properties",
+            "--properties-utf8",
+            "-O",
+            "strings",
+        ],
+        ["strings_ja_JP.properties"],
+    );
+
+    assert_eq!(
+        en_us.unwrap(),
+        b"app.price=\\= 5 \\u20AC\napp.title=O\xF9\\: here\n".to_vec()
+    );
+    assert_eq!(
+        text(&ja_jp),
+        "app.price=5 \\u20AC\napp.title=\\u3069\\u3053\n"
+    );
+    assert_eq!(text(&utf8), "app.price=5 €\napp.title=どこ\n");
+}
+
+#[test]
+fn it_writes_resx_files_with_descriptions_as_comments() {
+    let [en_us, fr_fr, description] = convert_csv(
+        "resx",
+        "id,description,en_US,fr-FR\nSave,Save button,Save & close,Enregistrer\nTitle,,<Untitled>,<Sans titre>\n",
+        &["--format", "resx", "--description-column", "description"],
+        ["Strings.en-US.resx", "Strings.fr-FR.resx", "Strings.description.resx"],
+    );
+    let en_us = text(&en_us);
+
+    assert!(en_us.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<root>\n"));
+    assert!(en_us.contains("<value>text/microsoft-resx</value>"));
+    assert!(en_us.ends_with(
+        "  <data name=\"Save\" xml:space=\"preserve\">\n    <value>Save &amp; close</value>\n    <comment>Save button</comment>\n  </data>\n  <data name=\"Title\" xml:space=\"preserve\">\n    <value>&lt;Untitled&gt;</value>\n  </data>\n</root>\n"
+    ));
+    assert!(text(&fr_fr).contains("<value>Enregistrer</value>\n    <comment>Save button</comment>"));
+    assert!(description.is_none());
+}
+
+#[test]
+fn it_writes_arb_files_with_metadata_from_the_source_locale() {
+    let [de_de, en_us] = convert_csv(
+        "arb",
+        "id,en-US,de_DE,description\ngreeting,Hello {name},Hallo,Greets the user\ntitle,My app,Meine App,\n",
+        &[
+            "--format",
+            "arb",
+            "--description-column",
+            "description",
+            "--source-locale",
+            "en-US",
+        ],
+        ["app_de_DE.arb", "app_en_US.arb"],
+    );
+    let en_us: serde_json::Value = serde_json::from_str(text(&en_us)).unwrap();
+
+    assert_eq!(
+        text(&de_de),
+        "{\n  \"@@locale\": \"de_DE\",\n  \"greeting\": \"Hallo\",\n  \"@greeting\": {\n    \"description\": \"Greets the user\",\n    \"placeholders\": {\n      \"name\": {}\n    }\n  },\n  \"title\": \"Meine App\"\n}\n"
+    );
+    assert_eq!(en_us["@@locale"], "en_US");
+    assert_eq!(en_us["greeting"], "Hello {name}");
+}
+
+#[test]
+fn it_writes_fluent_files() {
+    let [en_us] = convert_csv(
+        "fluent",
+        "id,en_US,notes\nlogin,Log in,Login button\nlogin.title,Log in to continue,\napp.about,\"Translocate\nmoves things\",\n",
+        &[
+            "--format",
+            "fluent",
+            "--description-column",
+            "notes",
+            "-O",
+            "main",
+        ],
+        ["en-US/main.ftl"],
+    );
+
+    assert_eq!(
+        text(&en_us),
+        "app-about =\n    Translocate\n    moves things\n# Login button\nlogin = Log in\n    .title = Log in to continue\n"
+    );
+}
+
+#[test]
+fn it_writes_webextension_messages() {
+    let [pt_br, en] = convert_csv(
+        "webextension",
+        "id,en,pt-BR,description\nextension.name,My extension,Minha extensão,Name in the store\ngreeting,Hello $USER$,Olá $USER$,\n",
+        &["--format", "webextension", "--description-column", "description"],
+        ["_locales/pt_BR/messages.json", "_locales/en/messages.json"],
+    );
+
+    assert!(en.is_some());
+    assert_eq!(
+        text(&pt_br),
+        "{\n  \"extension_name\": {\n    \"message\": \"Minha extensão\",\n    \"description\": \"Name in the store\"\n  },\n  \"greeting\": {\n    \"message\": \"Olá $USER$\",\n    \"placeholders\": {\n      \"user\": {\n        \"content\": \"$1\"\n      }\n    }\n  }\n}\n"
+    );
+}
+
+#[test]
+fn it_writes_a_string_catalog_with_plural_variants() {
+    let [catalog, stringsdict] = convert_csv(
+        "xcstrings",
+        "id,en,de,comment\ntitle,Inbox,Posteingang,Screen title\nitems.one,%lld message,%lld Nachricht,\nitems.other,%lld messages,,\n",
+        &[
+            "--format",
+            "xcstrings",
+            "--description-column",
+            "comment",
+            "--source-locale",
+            "en",
+        ],
+        ["Localizable.xcstrings", "en.lproj/Localizable.stringsdict"],
+    );
+    let catalog: serde_json::Value = serde_json::from_str(text(&catalog)).unwrap();
+    let stringsdict = text(&stringsdict);
+
+    assert_eq!(
+        catalog,
+        serde_json::json!({
+            "sourceLanguage": "en",
+            "strings": {
+                "items": {
+                    "extractionState": "manual",
+                    "localizations": {
+                        "de": { "variations": { "plural": {
+                            "one": { "stringUnit": { "state": "translated", "value": "%lld Nachricht" } },
+                            "other": { "stringUnit": { "state": "new", "value": "" } }
+                        } } },
+                        "en": { "variations": { "plural": {
+                            "one": { "stringUnit": { "state": "translated", "value": "%lld message" } },
+                            "other": { "stringUnit": { "state": "translated", "value": "%lld messages" } }
+                        } } }
+                    }
+                },
+                "title": {
+                    "comment": "Screen title",
+                    "extractionState": "manual",
+                    "localizations": {
+                        "de": { "stringUnit": { "state": "translated", "value": "Posteingang" } },
+                        "en": { "stringUnit": { "state": "translated", "value": "Inbox" } }
+                    }
+                }
+            },
+            "version": "1.0"
+        })
+    );
+    assert!(stringsdict.contains(
+        "\t<key>items</key>\n\t<dict>\n\t\t<key>NSStringLocalizedFormatKey</key>\n\t\t<string>%#@value@</string>\n"
+    ));
+    assert!(stringsdict.contains(
+        "\t\t\t<key>NSStringFormatValueTypeKey</key>\n\t\t\t<string>lld</string>\n\t\t\t<key>one</key>\n\t\t\t<string>%lld message</string>\n"
+    ));
+    assert!(!stringsdict.contains("title"));
+}
+
+#[test]
+fn it_writes_qt_linguist_files_grouped_by_context() {
+    let [de, en] = convert_csv(
+        "qt",
+        "id,class,en,de\nsave,Editor,Save,Speichern\nmenu.quit,,Quit & exit,\n",
+        &["--format", "qt", "--context-column", "class"],
+        ["app_de.ts", "app_en.ts"],
+    );
+
+    assert!(en.is_some());
+    assert_eq!(
+        text(&de),
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE TS>\n\
+         <TS version=\"2.1\" language=\"de\" sourcelanguage=\"en\">\n\
+         <context>\n    <name>Editor</name>\n\
+         \x20   <message id=\"save\">\n        <source>Save</source>\n\
+         \x20       <translation>Speichern</translation>\n    </message>\n\
+         </context>\n\
+         <context>\n    <name>menu</name>\n\
+         \x20   <message id=\"menu.quit\">\n        <source>Quit &amp; exit</source>\n\
+         \x20       <translation type=\"unfinished\"></translation>\n    </message>\n\
+         </context>\n</TS>\n"
+    );
+}